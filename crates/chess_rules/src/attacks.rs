@@ -0,0 +1,244 @@
+//! Precomputed attack tables. Knights, kings and pawns attack the same squares no matter what else
+//! is on the board. Bishops and rooks are looked up with magic bitboards: the blockers on their
+//! rays are multiplied by a magic number that maps every blocker arrangement to its own table slot
+
+use std::sync::OnceLock;
+
+use crate::{Bitboard, PieceColor, Position};
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+	(1, 2),
+	(2, 1),
+	(-1, 2),
+	(-2, 1),
+	(1, -2),
+	(2, -1),
+	(-1, -2),
+	(-2, -1),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+	(1, 0),
+	(-1, 0),
+	(0, 1),
+	(0, -1),
+	(1, 1),
+	(1, -1),
+	(-1, 1),
+	(-1, -1),
+];
+
+/// The lookup data of one square for one kind of sliding piece
+#[derive(Debug, Clone, Copy)]
+struct Magic {
+	/// Squares whose occupancy changes the attacks, which leaves out the board edges
+	mask: Bitboard,
+	magic: u64,
+	shift: u32,
+	offset: usize,
+}
+
+impl Magic {
+	fn index(&self, occupied: Bitboard) -> usize {
+		((occupied & self.mask).0.wrapping_mul(self.magic) >> self.shift) as usize + self.offset
+	}
+}
+
+#[derive(Debug)]
+struct SlidingAttacks {
+	magics: [Magic; 64],
+	table: Vec<Bitboard>,
+}
+
+impl SlidingAttacks {
+	fn new(directions: [(i8, i8); 4], rng: &mut Xorshift) -> Self {
+		let mut table = Vec::new();
+		let magics =
+			std::array::from_fn(|square| find_magic(square as i8, directions, rng, &mut table));
+		Self { magics, table }
+	}
+
+	fn attacks(&self, square: i8, occupied: Bitboard) -> Bitboard {
+		self.table[self.magics[square as usize].index(occupied)]
+	}
+}
+
+#[derive(Debug)]
+struct AttackTables {
+	knight: [Bitboard; 64],
+	king: [Bitboard; 64],
+	pawn: [[Bitboard; 64]; 2],
+	bishop: SlidingAttacks,
+	rook: SlidingAttacks,
+}
+
+impl AttackTables {
+	fn new() -> Self {
+		// A fixed seed makes the magic search take the same path every time
+		let mut rng = Xorshift(0x9E37_79B9_7F4A_7C15);
+
+		Self {
+			knight: std::array::from_fn(|square| step_attacks(square as i8, &KNIGHT_JUMPS)),
+			king: std::array::from_fn(|square| step_attacks(square as i8, &KING_STEPS)),
+			pawn: [1, -1].map(|forward| {
+				std::array::from_fn(|square| {
+					step_attacks(square as i8, &[(forward, -1), (forward, 1)])
+				})
+			}),
+			bishop: SlidingAttacks::new(BISHOP_DIRECTIONS, &mut rng),
+			rook: SlidingAttacks::new(ROOK_DIRECTIONS, &mut rng),
+		}
+	}
+}
+
+/// The tables are the same for every board, so they are only computed once
+fn tables() -> &'static AttackTables {
+	static TABLES: OnceLock<AttackTables> = OnceLock::new();
+	TABLES.get_or_init(AttackTables::new)
+}
+
+pub fn knight_attacks(square: i8) -> Bitboard {
+	tables().knight[square as usize]
+}
+
+pub fn king_attacks(square: i8) -> Bitboard {
+	tables().king[square as usize]
+}
+
+/// Squares attacked by a pawn of `color`, which unlike its moves only ever go diagonally forward
+pub fn pawn_attacks(color: PieceColor, square: i8) -> Bitboard {
+	tables().pawn[color as usize][square as usize]
+}
+
+pub fn bishop_attacks(square: i8, occupied: Bitboard) -> Bitboard {
+	tables().bishop.attacks(square, occupied)
+}
+
+pub fn rook_attacks(square: i8, occupied: Bitboard) -> Bitboard {
+	tables().rook.attacks(square, occupied)
+}
+
+pub fn queen_attacks(square: i8, occupied: Bitboard) -> Bitboard {
+	bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+fn step_attacks(square: i8, steps: &[(i8, i8)]) -> Bitboard {
+	let start = Position::from_index(square);
+	steps
+		.iter()
+		.map(|(row, col)| Position::new(start.row + row, start.col + col))
+		.filter(|target| target.is_on_board())
+		.fold(Bitboard::EMPTY, |attacks, target| {
+			attacks | Bitboard::from_square(target.index())
+		})
+}
+
+/// Walks every ray until it leaves the board or hits an occupied square, which is still attacked.
+/// Only used to fill the tables
+fn ray_attacks(square: i8, occupied: Bitboard, directions: [(i8, i8); 4]) -> Bitboard {
+	let start = Position::from_index(square);
+	let mut attacks = Bitboard::EMPTY;
+
+	for (row, col) in directions {
+		let mut target = Position::new(start.row + row, start.col + col);
+		while target.is_on_board() {
+			attacks |= Bitboard::from_square(target.index());
+			if occupied.contains(target.index()) {
+				break;
+			}
+			target = Position::new(target.row + row, target.col + col);
+		}
+	}
+	attacks
+}
+
+/// The squares of the rays that can hold a blocker. The last square of a ray is attacked whether
+/// or not something stands on it, so it's left out
+fn relevant_mask(square: i8, directions: [(i8, i8); 4]) -> Bitboard {
+	let start = Position::from_index(square);
+	let mut mask = Bitboard::EMPTY;
+
+	for (row, col) in directions {
+		let mut target = Position::new(start.row + row, start.col + col);
+		while Position::new(target.row + row, target.col + col).is_on_board() {
+			mask |= Bitboard::from_square(target.index());
+			target = Position::new(target.row + row, target.col + col);
+		}
+	}
+	mask
+}
+
+/// Tries random sparse numbers until one maps every blocker arrangement to a slot that holds its
+/// attacks, then appends that square's slots to `table`
+fn find_magic(
+	square: i8,
+	directions: [(i8, i8); 4],
+	rng: &mut Xorshift,
+	table: &mut Vec<Bitboard>,
+) -> Magic {
+	let mask = relevant_mask(square, directions);
+	let shift = 64 - mask.count();
+
+	// Every subset of the mask, enumerated with the carry-rippler trick
+	let mut occupancies = Vec::new();
+	let mut subset = 0u64;
+	loop {
+		occupancies.push(Bitboard(subset));
+		subset = subset.wrapping_sub(mask.0) & mask.0;
+		if subset == 0 {
+			break;
+		}
+	}
+	let attacks: Vec<Bitboard> = occupancies
+		.iter()
+		.map(|&occupied| ray_attacks(square, occupied, directions))
+		.collect();
+
+	let mut slots = vec![None; 1 << mask.count()];
+	loop {
+		let magic = rng.next_u64() & rng.next_u64() & rng.next_u64();
+		// Magics that don't spread the mask over the top bits are hopeless, skip them early
+		if (mask.0.wrapping_mul(magic) >> 56).count_ones() < 6 {
+			continue;
+		}
+
+		let candidate = Magic {
+			mask,
+			magic,
+			shift,
+			offset: 0,
+		};
+		slots.fill(None);
+		let fits = occupancies
+			.iter()
+			.zip(&attacks)
+			.all(|(&occupied, &attack)| {
+				let slot = &mut slots[candidate.index(occupied)];
+				// Two arrangements may share a slot as long as they have the same attacks
+				*slot.get_or_insert(attack) == attack
+			});
+
+		if fits {
+			let offset = table.len();
+			table.extend(slots.iter().map(|x| x.unwrap_or_default()));
+			return Magic {
+				offset,
+				..candidate
+			};
+		}
+	}
+}
+
+/// Small deterministic random number generator for the magic search
+#[derive(Debug)]
+struct Xorshift(u64);
+
+impl Xorshift {
+	fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+	}
+}
@@ -0,0 +1,79 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+/// A set of squares, one bit per square with bit `0` being a1 and bit `63` being h8
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+	pub const EMPTY: Self = Self(0);
+	pub const FULL: Self = Self(u64::MAX);
+	/// The squares of the same colour as a1
+	pub const DARK_SQUARES: Self = Self(0xAA55_AA55_AA55_AA55);
+	pub const LIGHT_SQUARES: Self = Self(!Self::DARK_SQUARES.0);
+
+	pub const fn from_square(square: i8) -> Self {
+		Self(1 << square)
+	}
+
+	pub const fn contains(self, square: i8) -> bool {
+		self.0 & (1 << square) != 0
+	}
+
+	pub const fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	pub const fn count(self) -> u32 {
+		self.0.count_ones()
+	}
+
+	/// The lowest square in the set
+	pub const fn first_square(self) -> Option<i8> {
+		if self.is_empty() {
+			None
+		} else {
+			Some(self.0.trailing_zeros() as i8)
+		}
+	}
+
+	/// Every square in the set, from a1 towards h8
+	pub fn squares(self) -> impl Iterator<Item = i8> {
+		let mut bits = self.0;
+		std::iter::from_fn(move || {
+			let square = Self(bits).first_square()?;
+			// Clears the lowest set bit
+			bits &= bits - 1;
+			Some(square)
+		})
+	}
+}
+
+macro_rules! impl_bit_op {
+	($op:ident, $fn:ident, $assign_op:ident, $assign_fn:ident) => {
+		impl $op for Bitboard {
+			type Output = Self;
+
+			fn $fn(self, rhs: Self) -> Self {
+				Self($op::$fn(self.0, rhs.0))
+			}
+		}
+
+		impl $assign_op for Bitboard {
+			fn $assign_fn(&mut self, rhs: Self) {
+				$assign_op::$assign_fn(&mut self.0, rhs.0);
+			}
+		}
+	};
+}
+
+impl_bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_bit_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+impl_bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl Not for Bitboard {
+	type Output = Self;
+
+	fn not(self) -> Self {
+		Self(!self.0)
+	}
+}
@@ -0,0 +1,346 @@
+use crate::{
+	movegen::{is_square_attacked, pseudo_legal_moves, MoveList},
+	moves::{MoveFlag, UndoInfo},
+	zobrist::{castling_key, piece_key, side_key},
+	Bitboard, GameOverReason, Move, Piece, PieceColor, Pieces, Position,
+};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+	pub white_kingside: bool,
+	pub white_queenside: bool,
+	pub black_kingside: bool,
+	pub black_queenside: bool,
+}
+
+impl CastlingRights {
+	/// Kingside and queenside rights of `color`
+	pub const fn for_color(self, color: PieceColor) -> (bool, bool) {
+		match color {
+			PieceColor::White => (self.white_kingside, self.white_queenside),
+			PieceColor::Black => (self.black_kingside, self.black_queenside),
+		}
+	}
+
+	/// Drops the rights lost by `piece` moving from `start_square` to `target_square`
+	pub fn update(&mut self, piece: Piece, start_square: i8, target_square: i8) {
+		if piece.piece_type == Pieces::King {
+			match piece.color {
+				PieceColor::White => {
+					self.white_kingside = false;
+					self.white_queenside = false;
+				}
+				PieceColor::Black => {
+					self.black_kingside = false;
+					self.black_queenside = false;
+				}
+			}
+		}
+
+		// A rook leaving its corner or getting captured there takes the right with it
+		for square in [start_square, target_square] {
+			match square {
+				0 => self.white_queenside = false,
+				7 => self.white_kingside = false,
+				56 => self.black_queenside = false,
+				63 => self.black_kingside = false,
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Everything needed to continue a game from a position: where the pieces stand, whose turn it is
+/// and which special moves are still available.
+///
+/// The Zobrist key is kept up to date by [`Board::set_piece`], [`Board::make_move`] and
+/// [`Board::unmake_move`]. After changing the public fields directly,
+/// [`Board::refresh_zobrist_key`] has to be called
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+	/// Square-indexed view of the board, kept in sync with the bitboards
+	squares: [Option<Piece>; 64],
+	/// Squares of each colour's pieces, indexed by [`PieceColor`]
+	by_color: [Bitboard; 2],
+	/// Squares of each kind of piece of both colours, indexed by [`Pieces`]
+	by_type: [Bitboard; 6],
+	pub side_to_move: PieceColor,
+	pub castling_rights: CastlingRights,
+	/// Square a pawn skipped over with a double push on the last move
+	pub en_passant: Option<i8>,
+	/// Moves since the last capture or pawn move, for the fifty-move rule
+	pub halfmove_clock: u32,
+	pub fullmove_number: u32,
+	zobrist_key: u64,
+}
+
+impl Default for Board {
+	fn default() -> Self {
+		Self::starting_position()
+	}
+}
+
+impl Board {
+	pub const STARTING_FEN: &'static str =
+		"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+	pub const fn empty() -> Self {
+		Self {
+			squares: [None; 64],
+			by_color: [Bitboard::EMPTY; 2],
+			by_type: [Bitboard::EMPTY; 6],
+			side_to_move: PieceColor::White,
+			castling_rights: CastlingRights {
+				white_kingside: false,
+				white_queenside: false,
+				black_kingside: false,
+				black_queenside: false,
+			},
+			en_passant: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			zobrist_key: side_key(PieceColor::White),
+		}
+	}
+
+	pub fn starting_position() -> Self {
+		Self::from_fen(Self::STARTING_FEN).expect("the starting position is a valid FEN")
+	}
+
+	pub fn piece_at(&self, square: i8) -> Option<Piece> {
+		self.squares[square as usize]
+	}
+
+	pub fn set_piece(&mut self, square: i8, piece: Option<Piece>) {
+		let bit = Bitboard::from_square(square);
+		if let Some(old) = self.squares[square as usize] {
+			self.by_color[old.color as usize] ^= bit;
+			self.by_type[old.piece_type as usize] ^= bit;
+			self.zobrist_key ^= piece_key(old, square);
+		}
+		if let Some(new) = piece {
+			self.by_color[new.color as usize] |= bit;
+			self.by_type[new.piece_type as usize] |= bit;
+			self.zobrist_key ^= piece_key(new, square);
+		}
+		self.squares[square as usize] = piece;
+	}
+
+	pub fn occupied(&self) -> Bitboard {
+		self.by_color[0] | self.by_color[1]
+	}
+
+	pub fn color_bitboard(&self, color: PieceColor) -> Bitboard {
+		self.by_color[color as usize]
+	}
+
+	/// Squares of every piece of the same kind and colour as `piece`
+	pub fn bitboard(&self, piece: Piece) -> Bitboard {
+		self.by_color[piece.color as usize] & self.by_type[piece.piece_type as usize]
+	}
+
+	/// Every piece on the board together with its square index
+	pub fn pieces(&self) -> impl Iterator<Item = (i8, Piece)> + '_ {
+		(0..)
+			.zip(self.squares)
+			.filter_map(|(index, piece)| Some((index, piece?)))
+	}
+
+	pub fn king_square(&self, color: PieceColor) -> Option<i8> {
+		self.bitboard(Piece::new(Pieces::King, color))
+			.first_square()
+	}
+
+	pub fn is_square_attacked(&self, square: i8, attacker_color: PieceColor) -> bool {
+		is_square_attacked(self, square, attacker_color)
+	}
+
+	pub fn is_in_check(&self, color: PieceColor) -> bool {
+		self.king_square(color)
+			.is_some_and(|king_square| self.is_square_attacked(king_square, color.not()))
+	}
+
+	/// Legal moves of the piece on `start_square`, if it belongs to the side to move
+	pub fn legal_moves_from(&self, start_square: i8) -> MoveList {
+		self.legal_moves_in(Bitboard::from_square(start_square))
+	}
+
+	pub fn legal_moves(&self) -> MoveList {
+		self.legal_moves_in(Bitboard::FULL)
+	}
+
+	pub fn has_legal_moves(&self) -> bool {
+		!self.legal_moves().is_empty()
+	}
+
+	fn legal_moves_in(&self, from_squares: Bitboard) -> MoveList {
+		let mut moves = MoveList::new();
+		pseudo_legal_moves(self, from_squares, &mut moves);
+
+		// Play every candidate move and drop the ones that leave our own king in check
+		let mut board = self.clone();
+		moves.retain(|mv| {
+			let undo_info = board.make_move(mv);
+			let in_check = board.is_in_check(self.side_to_move);
+			board.unmake_move(mv, undo_info);
+			!in_check
+		});
+
+		moves
+	}
+
+	/// Plays `mv` for the side to move. The returned [`UndoInfo`] together with the move is
+	/// enough for [`Board::unmake_move`] to restore the board exactly as it was
+	pub fn make_move(&mut self, mv: Move) -> UndoInfo {
+		let undo_info = UndoInfo {
+			castling_rights: self.castling_rights,
+			en_passant: self.en_passant,
+			halfmove_clock: self.halfmove_clock,
+			zobrist_key: self.zobrist_key,
+		};
+
+		// Take out the parts of the key that are about to change, the pieces update it themselves
+		self.zobrist_key ^= self.en_passant_key()
+			^ castling_key(self.castling_rights)
+			^ side_key(PieceColor::White);
+
+		if let Some((rook_start, rook_target)) = mv.castling_rook_squares() {
+			self.set_piece(rook_target, self.piece_at(rook_start));
+			self.set_piece(rook_start, None);
+		}
+
+		if let Some(captured_square) = mv.en_passant_capture_square() {
+			self.set_piece(captured_square, None);
+		}
+
+		// Only a double push right before can be answered with an en passant capture
+		self.en_passant = (mv.flag == MoveFlag::DoublePush).then_some((mv.from + mv.to) / 2);
+
+		self.castling_rights.update(mv.piece, mv.from, mv.to);
+
+		if mv.piece.piece_type == Pieces::Pawn || mv.is_capture() {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock += 1;
+		}
+		if mv.piece.color == PieceColor::Black {
+			self.fullmove_number += 1;
+		}
+
+		let piece_type = mv.promotion.unwrap_or(mv.piece.piece_type);
+		self.set_piece(mv.from, None);
+		self.set_piece(mv.to, Some(Piece::new(piece_type, mv.piece.color)));
+		self.side_to_move = mv.piece.color.not();
+
+		self.zobrist_key ^= self.en_passant_key() ^ castling_key(self.castling_rights);
+
+		undo_info
+	}
+
+	/// Takes back `mv`, which has to be the last move made on this board
+	pub fn unmake_move(&mut self, mv: Move, undo_info: UndoInfo) {
+		self.side_to_move = mv.piece.color;
+		if mv.piece.color == PieceColor::Black {
+			self.fullmove_number -= 1;
+		}
+
+		self.set_piece(mv.from, Some(mv.piece));
+		if let Some(captured_square) = mv.en_passant_capture_square() {
+			self.set_piece(mv.to, None);
+			self.set_piece(captured_square, mv.captured);
+		} else {
+			self.set_piece(mv.to, mv.captured);
+		}
+
+		if let Some((rook_start, rook_target)) = mv.castling_rook_squares() {
+			self.set_piece(rook_start, self.piece_at(rook_target));
+			self.set_piece(rook_target, None);
+		}
+
+		self.castling_rights = undo_info.castling_rights;
+		self.en_passant = undo_info.en_passant;
+		self.halfmove_clock = undo_info.halfmove_clock;
+		self.zobrist_key = undo_info.zobrist_key;
+	}
+
+	/// Whether neither side has the material left to ever deliver mate: bare kings, a single minor
+	/// piece, or only bishops that all stand on squares of the same colour
+	pub fn is_insufficient_material(&self) -> bool {
+		let mut minor_pieces = 0;
+		let mut bishop_square_colors = Vec::new();
+
+		for (index, piece) in self.pieces() {
+			match piece.piece_type {
+				Pieces::King => {}
+				Pieces::Knight => minor_pieces += 1,
+				Pieces::Bishop => {
+					minor_pieces += 1;
+					let pos = Position::from_index(index);
+					bishop_square_colors.push((pos.row + pos.col) % 2);
+				}
+				Pieces::Queen | Pieces::Rook | Pieces::Pawn => return false,
+			}
+		}
+
+		minor_pieces <= 1
+			|| (bishop_square_colors.len() == minor_pieces
+				&& bishop_square_colors.windows(2).all(|x| x[0] == x[1]))
+	}
+
+	/// Whether `color` could never deliver mate, however badly its opponent played. This decides
+	/// a game lost on time as a draw. A lone knight or same coloured bishops only can't mate, unless
+	/// the opponent's own pieces could block its king in
+	pub fn has_insufficient_material(&self, color: PieceColor) -> bool {
+		let own = self.color_bitboard(color);
+		let opponent = self.color_bitboard(color.not());
+		let of_type = |piece_type: Pieces| self.by_type[piece_type as usize];
+
+		let heavy = of_type(Pieces::Pawn) | of_type(Pieces::Rook) | of_type(Pieces::Queen);
+		if !(own & heavy).is_empty() {
+			return false;
+		}
+
+		if !(own & of_type(Pieces::Knight)).is_empty() {
+			let blockers = opponent & !of_type(Pieces::King) & !of_type(Pieces::Queen);
+			return own.count() <= 2 && blockers.is_empty();
+		}
+
+		let bishops = of_type(Pieces::Bishop);
+		if !(own & bishops).is_empty() {
+			let same_colour = (bishops & Bitboard::DARK_SQUARES).is_empty()
+				|| (bishops & Bitboard::LIGHT_SQUARES).is_empty();
+			return same_colour
+				&& of_type(Pieces::Knight).is_empty()
+				&& of_type(Pieces::Pawn).is_empty();
+		}
+
+		true
+	}
+
+	/// The draw the rules call for in this position, given the keys of every position of the game
+	/// up to and including this one. Mate and stalemate are left to the caller, they take
+	/// precedence over any of these
+	pub fn draw_reason(&self, keys: &[u64]) -> Option<GameOverReason> {
+		let repetitions = keys.iter().filter(|&&x| x == self.zobrist_key).count();
+		if self.halfmove_clock >= 100 {
+			Some(GameOverReason::FiftyMoveRule)
+		} else if repetitions >= 3 {
+			Some(GameOverReason::ThreefoldRepetition)
+		} else if self.is_insufficient_material() {
+			Some(GameOverReason::InsufficientMaterial)
+		} else {
+			None
+		}
+	}
+
+	/// Identifies the position for repetition detection, transposition tables and opening books.
+	/// Two positions with the same key have the same pieces on the same squares, the same side to
+	/// move and the same castling and en passant possibilities
+	pub const fn zobrist_key(&self) -> u64 {
+		self.zobrist_key
+	}
+
+	pub fn refresh_zobrist_key(&mut self) {
+		self.zobrist_key = self.compute_zobrist_key();
+	}
+}
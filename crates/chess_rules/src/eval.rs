@@ -0,0 +1,207 @@
+use crate::{
+	bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+	Bitboard, Board, EvalParams, Piece, PieceColor, Pieces, Position, Weight, BOARD_SIZE,
+};
+
+/// How much each kind of piece counts towards the game phase, indexed by [`Pieces`]
+const PHASE_WEIGHTS: [i32; 6] = [0, 4, 1, 1, 2, 0];
+/// Phase with every piece but the pawns on the board. Below it the endgame weights take over
+const OPENING_PHASE: i32 = 24;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+/// Worth of a piece in centipawns for ordering captures. The king can't be traded, so its value
+/// only matters for preferring the cheapest attacker
+pub(crate) const fn piece_value(piece_type: Pieces) -> i32 {
+	match piece_type {
+		Pieces::Pawn => 100,
+		Pieces::Knight => 320,
+		Pieces::Bishop => 330,
+		Pieces::Rook => 500,
+		Pieces::Queen => 900,
+		Pieces::King => 20_000,
+	}
+}
+
+/// Weights one side earned, and the squares its pieces attack
+struct SideTerms {
+	weight: Weight,
+	attacks: Bitboard,
+	phase: i32,
+}
+
+impl Board {
+	/// Static evaluation in centipawns from the side to move's point of view, with the built-in
+	/// weights
+	pub fn evaluate(&self) -> i32 {
+		self.evaluate_with(EvalParams::built_in())
+	}
+
+	/// Static evaluation in centipawns from the side to move's point of view. Every term has a
+	/// middlegame and an endgame weight, blended by the material left on the board
+	pub fn evaluate_with(&self, params: &EvalParams) -> i32 {
+		let white = self.side_terms(PieceColor::White, params);
+		let black = self.side_terms(PieceColor::Black, params);
+
+		let mut weight = white.weight - black.weight;
+		weight += self.king_safety(PieceColor::White, black.attacks, params);
+		weight -= self.king_safety(PieceColor::Black, white.attacks, params);
+
+		let phase = (white.phase + black.phase).min(OPENING_PHASE);
+		let score =
+			(weight.middlegame * phase + weight.endgame * (OPENING_PHASE - phase)) / OPENING_PHASE;
+
+		match self.side_to_move {
+			PieceColor::White => score,
+			PieceColor::Black => -score,
+		}
+	}
+
+	/// Material, piece-square tables, mobility, pawn structure and the bishop pair of `color`
+	fn side_terms(&self, color: PieceColor, params: &EvalParams) -> SideTerms {
+		let own = self.color_bitboard(color);
+		let occupied = self.occupied();
+		let own_pawns = self.bitboard(Piece::new(Pieces::Pawn, color));
+		let opponent_pawns = self.bitboard(Piece::new(Pieces::Pawn, color.not()));
+
+		let mut terms = SideTerms {
+			weight: Weight::default(),
+			attacks: Bitboard::EMPTY,
+			phase: 0,
+		};
+
+		for square in own.squares() {
+			let Some(piece) = self.piece_at(square) else {
+				continue;
+			};
+			let piece_type = piece.piece_type;
+			let Position { row, col } = Position::from_index(square);
+			// The tables start at the far rank of the side they're for
+			let row_from_top = match color {
+				PieceColor::White => BOARD_SIZE - 1 - row,
+				PieceColor::Black => row,
+			};
+			let index = (row_from_top * BOARD_SIZE + col) as usize;
+
+			terms.weight += params.material[piece_type as usize];
+			terms.weight += params.piece_square[piece_type as usize][index];
+			terms.phase += PHASE_WEIGHTS[piece_type as usize];
+
+			let reach = match piece_type {
+				Pieces::Pawn => pawn_attacks(color, square),
+				Pieces::Knight => knight_attacks(square),
+				Pieces::Bishop => bishop_attacks(square, occupied),
+				Pieces::Rook => rook_attacks(square, occupied),
+				Pieces::Queen => queen_attacks(square, occupied),
+				Pieces::King => king_attacks(square),
+			};
+			terms.attacks |= reach;
+			match piece_type {
+				Pieces::Pawn => {
+					terms.weight +=
+						pawn_structure(color, row, col, own_pawns, opponent_pawns, params);
+				}
+				Pieces::King => {}
+				_ => {
+					let moves = (reach & !own).count() as i32;
+					terms.weight += params.mobility[piece_type as usize] * moves;
+				}
+			}
+		}
+
+		if self.bitboard(Piece::new(Pieces::Bishop, color)).count() >= 2 {
+			terms.weight += params.bishop_pair;
+		}
+
+		terms
+	}
+
+	/// Pawns in front of `color`'s king and opposing attacks on the squares around it
+	fn king_safety(
+		&self,
+		color: PieceColor,
+		opponent_attacks: Bitboard,
+		params: &EvalParams,
+	) -> Weight {
+		let Some(king_square) = self.king_square(color) else {
+			return Weight::default();
+		};
+		let Position { row, col } = Position::from_index(king_square);
+		let forward = match color {
+			PieceColor::White => 1,
+			PieceColor::Black => -1,
+		};
+
+		let own_pawns = self.bitboard(Piece::new(Pieces::Pawn, color));
+		let shield_rows = row_mask(row + forward) | row_mask(row + 2 * forward);
+		let shield_files = file(col) | neighbour_files(col);
+		let shield = (own_pawns & shield_rows & shield_files).count() as i32;
+		let attacked = (king_attacks(king_square) & opponent_attacks).count() as i32;
+
+		params.pawn_shield * shield + params.king_attacked_square * attacked
+	}
+}
+
+/// Doubled, isolated and passed pawn weights of `color`'s pawn on `row` and `col`
+fn pawn_structure(
+	color: PieceColor,
+	row: i8,
+	col: i8,
+	own_pawns: Bitboard,
+	opponent_pawns: Bitboard,
+	params: &EvalParams,
+) -> Weight {
+	let mut weight = Weight::default();
+	let ahead = rows_ahead(color, row);
+
+	if !(own_pawns & file(col) & ahead).is_empty() {
+		weight += params.doubled_pawn;
+	}
+	if (own_pawns & neighbour_files(col)).is_empty() {
+		weight += params.isolated_pawn;
+	}
+
+	let stoppers = opponent_pawns & (file(col) | neighbour_files(col)) & ahead;
+	if stoppers.is_empty() {
+		let rank = match color {
+			PieceColor::White => row,
+			PieceColor::Black => BOARD_SIZE - 1 - row,
+		};
+		// The list starts at the second rank, pawns never stand on the first
+		let bonus = (rank as usize)
+			.checked_sub(1)
+			.and_then(|x| params.passed_pawn.get(x));
+		weight += bonus.copied().unwrap_or_default();
+	}
+
+	weight
+}
+
+fn file(col: i8) -> Bitboard {
+	if (0..BOARD_SIZE).contains(&col) {
+		Bitboard(FILE_A << col)
+	} else {
+		Bitboard::EMPTY
+	}
+}
+
+fn neighbour_files(col: i8) -> Bitboard {
+	file(col - 1) | file(col + 1)
+}
+
+fn row_mask(row: i8) -> Bitboard {
+	if (0..BOARD_SIZE).contains(&row) {
+		Bitboard(0xFF << (row * BOARD_SIZE))
+	} else {
+		Bitboard::EMPTY
+	}
+}
+
+/// Every row in front of `row`, seen from `color`'s side
+fn rows_ahead(color: PieceColor, row: i8) -> Bitboard {
+	match color {
+		PieceColor::White if row >= BOARD_SIZE - 1 => Bitboard::EMPTY,
+		PieceColor::White => Bitboard(u64::MAX << ((row + 1) * BOARD_SIZE)),
+		PieceColor::Black => Bitboard((1 << (row * BOARD_SIZE)) - 1),
+	}
+}
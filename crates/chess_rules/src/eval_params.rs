@@ -0,0 +1,297 @@
+use std::{
+	error::Error,
+	fmt,
+	ops::{Add, AddAssign, Mul, Sub, SubAssign},
+	slice,
+	str::FromStr,
+	sync::OnceLock,
+};
+
+use crate::Pieces;
+
+/// The weights the evaluation uses unless others are loaded, in the same format as a file
+const BUILT_IN: &str = include_str!("../eval.toml");
+
+/// A weight with one value for the middlegame and one for the endgame, in centipawns
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Weight {
+	pub middlegame: i32,
+	pub endgame: i32,
+}
+
+impl Weight {
+	pub const fn new(middlegame: i32, endgame: i32) -> Self {
+		Self {
+			middlegame,
+			endgame,
+		}
+	}
+}
+
+impl Add for Weight {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self {
+		Self::new(self.middlegame + rhs.middlegame, self.endgame + rhs.endgame)
+	}
+}
+
+impl AddAssign for Weight {
+	fn add_assign(&mut self, rhs: Self) {
+		*self = *self + rhs;
+	}
+}
+
+impl Sub for Weight {
+	type Output = Self;
+
+	fn sub(self, rhs: Self) -> Self {
+		Self::new(self.middlegame - rhs.middlegame, self.endgame - rhs.endgame)
+	}
+}
+
+impl SubAssign for Weight {
+	fn sub_assign(&mut self, rhs: Self) {
+		*self = *self - rhs;
+	}
+}
+
+impl Mul<i32> for Weight {
+	type Output = Self;
+
+	fn mul(self, rhs: i32) -> Self {
+		Self::new(self.middlegame * rhs, self.endgame * rhs)
+	}
+}
+
+/// Every weight of the evaluation. They're read from a small TOML file, see `eval.toml` in this
+/// crate for the built-in values and what each of them is for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+	/// Indexed by [`Pieces`], the king's value is never used
+	pub material: [Weight; 6],
+	/// Indexed by [`Pieces`], then by square as seen from white's side with a8 first
+	pub piece_square: [[Weight; 64]; 6],
+	pub doubled_pawn: Weight,
+	pub isolated_pawn: Weight,
+	/// By the pawn's rank, from the second to the seventh
+	pub passed_pawn: [Weight; 6],
+	pub pawn_shield: Weight,
+	pub king_attacked_square: Weight,
+	/// Indexed by [`Pieces`], only knights, bishops, rooks and queens get it
+	pub mobility: [Weight; 6],
+	pub bishop_pair: Weight,
+}
+
+impl EvalParams {
+	const fn zero() -> Self {
+		Self {
+			material: [Weight::new(0, 0); 6],
+			piece_square: [[Weight::new(0, 0); 64]; 6],
+			doubled_pawn: Weight::new(0, 0),
+			isolated_pawn: Weight::new(0, 0),
+			passed_pawn: [Weight::new(0, 0); 6],
+			pawn_shield: Weight::new(0, 0),
+			king_attacked_square: Weight::new(0, 0),
+			mobility: [Weight::new(0, 0); 6],
+			bishop_pair: Weight::new(0, 0),
+		}
+	}
+
+	/// The weights shipped with the crate
+	pub fn built_in() -> &'static Self {
+		static BUILT_IN_PARAMS: OnceLock<EvalParams> = OnceLock::new();
+		BUILT_IN_PARAMS.get_or_init(|| {
+			let mut params = Self::zero();
+			params
+				.apply(BUILT_IN)
+				.expect("the built-in evaluation parameters are valid");
+			params
+		})
+	}
+
+	/// Overwrites the weights named in `text`
+	fn apply(&mut self, text: &str) -> Result<(), EvalParamsError> {
+		let mut section = "";
+		let mut lines = text.lines().map(strip_comment).enumerate();
+
+		while let Some((index, line)) = lines.next() {
+			let error = |kind| EvalParamsError {
+				line: index + 1,
+				kind,
+			};
+			if line.is_empty() {
+				continue;
+			}
+			if let Some(name) = line.strip_prefix('[') {
+				section = name
+					.strip_suffix(']')
+					.ok_or(error(EvalParamsErrorKind::Syntax))?
+					.trim();
+				continue;
+			}
+
+			let (name, value) = line
+				.split_once('=')
+				.ok_or(error(EvalParamsErrorKind::Syntax))?;
+			let mut value = value.trim().to_string();
+			// A list goes on over as many lines as it needs
+			if value.starts_with('[') {
+				while !value.ends_with(']') {
+					let (_, next) = lines
+						.next()
+						.ok_or(error(EvalParamsErrorKind::UnclosedList))?;
+					value.push(' ');
+					value.push_str(next);
+				}
+			}
+
+			let list = value
+				.strip_prefix('[')
+				.and_then(|x| x.strip_suffix(']'))
+				.unwrap_or(&value);
+			let values = list
+				.split(',')
+				.map(str::trim)
+				.filter(|x| !x.is_empty())
+				.map(|x| {
+					x.parse()
+						.map_err(|_| error(EvalParamsErrorKind::InvalidNumber(x.to_string())))
+				})
+				.collect::<Result<Vec<i32>, _>>()?;
+
+			let key = format!("{section}.{}", name.trim());
+			self.set(&key, &values).map_err(error)?;
+		}
+
+		Ok(())
+	}
+
+	fn set(&mut self, key: &str, values: &[i32]) -> Result<(), EvalParamsErrorKind> {
+		let unknown = || EvalParamsErrorKind::UnknownKey(key.to_string());
+		let (section, name) = key.split_once('.').ok_or_else(unknown)?;
+		let piece = piece_from_name(name);
+
+		// Tables list all middlegame values before the endgame ones, everything else is pairs
+		let (weights, is_table) = match (section, name, piece) {
+			("material", _, Some(piece_type)) if piece_type != Pieces::King => (
+				slice::from_mut(&mut self.material[piece_type as usize]),
+				false,
+			),
+			("piece_square", _, Some(piece_type)) => {
+				(&mut self.piece_square[piece_type as usize][..], true)
+			}
+			("pawns", "doubled", _) => (slice::from_mut(&mut self.doubled_pawn), false),
+			("pawns", "isolated", _) => (slice::from_mut(&mut self.isolated_pawn), false),
+			("pawns", "passed", _) => (&mut self.passed_pawn[..], false),
+			("king_safety", "pawn_shield", _) => (slice::from_mut(&mut self.pawn_shield), false),
+			("king_safety", "attacked_square", _) => {
+				(slice::from_mut(&mut self.king_attacked_square), false)
+			}
+			(
+				"mobility",
+				_,
+				Some(piece_type @ (Pieces::Knight | Pieces::Bishop | Pieces::Rook | Pieces::Queen)),
+			) => (
+				slice::from_mut(&mut self.mobility[piece_type as usize]),
+				false,
+			),
+			("bishops", "pair", _) => (slice::from_mut(&mut self.bishop_pair), false),
+			_ => return Err(unknown()),
+		};
+
+		if values.len() != weights.len() * 2 {
+			return Err(EvalParamsErrorKind::WrongCount {
+				key: key.to_string(),
+				expected: weights.len() * 2,
+				found: values.len(),
+			});
+		}
+
+		let count = weights.len();
+		for (index, weight) in weights.iter_mut().enumerate() {
+			*weight = if is_table {
+				Weight::new(values[index], values[count + index])
+			} else {
+				Weight::new(values[index * 2], values[index * 2 + 1])
+			};
+		}
+		Ok(())
+	}
+}
+
+impl Default for EvalParams {
+	fn default() -> Self {
+		Self::built_in().clone()
+	}
+}
+
+/// Reads a parameter file. Weights the file leaves out keep their built-in values
+impl FromStr for EvalParams {
+	type Err = EvalParamsError;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut params = Self::default();
+		params.apply(text)?;
+		Ok(params)
+	}
+}
+
+/// What went wrong reading a parameter file, and on which of its lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParamsError {
+	/// Counted from 1
+	pub line: usize,
+	pub kind: EvalParamsErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalParamsErrorKind {
+	/// A line that is neither a `[section]` nor a `name = value` pair
+	Syntax,
+	/// A list whose `]` never comes
+	UnclosedList,
+	InvalidNumber(String),
+	UnknownKey(String),
+	/// A weight given the wrong number of values
+	WrongCount {
+		key: String,
+		expected: usize,
+		found: usize,
+	},
+}
+
+impl fmt::Display for EvalParamsError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: ", self.line)?;
+		match &self.kind {
+			EvalParamsErrorKind::Syntax => write!(f, "expected a [section] or name = value"),
+			EvalParamsErrorKind::UnclosedList => write!(f, "list is never closed"),
+			EvalParamsErrorKind::InvalidNumber(number) => write!(f, "invalid number '{number}'"),
+			EvalParamsErrorKind::UnknownKey(key) => write!(f, "unknown weight '{key}'"),
+			EvalParamsErrorKind::WrongCount {
+				key,
+				expected,
+				found,
+			} => write!(f, "'{key}' needs {expected} values, found {found}"),
+		}
+	}
+}
+
+impl Error for EvalParamsError {}
+
+fn strip_comment(line: &str) -> &str {
+	line.split_once('#').map_or(line, |x| x.0).trim()
+}
+
+fn piece_from_name(name: &str) -> Option<Pieces> {
+	match name {
+		"king" => Some(Pieces::King),
+		"queen" => Some(Pieces::Queen),
+		"bishop" => Some(Pieces::Bishop),
+		"knight" => Some(Pieces::Knight),
+		"rook" => Some(Pieces::Rook),
+		"pawn" => Some(Pieces::Pawn),
+		_ => None,
+	}
+}
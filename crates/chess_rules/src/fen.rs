@@ -0,0 +1,310 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{Board, CastlingRights, Piece, PieceColor, Pieces, Position, BOARD_SIZE};
+
+/// Everything that can be wrong with a FEN string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+	/// A FEN has exactly six space separated fields
+	WrongFieldCount(usize),
+	/// The piece placement has to describe exactly eight ranks
+	WrongRankCount(usize),
+	/// A rank, counted from 8 down to 1, doesn't add up to eight squares
+	BadRankLength {
+		rank: i8,
+		length: usize,
+	},
+	InvalidPieceSymbol(char),
+	InvalidSideToMove(String),
+	InvalidCastling(String),
+	InvalidEnPassant(String),
+	InvalidHalfmoveClock(String),
+	InvalidFullmoveNumber(String),
+	/// Each side needs exactly one king
+	KingCount {
+		color: PieceColor,
+		count: usize,
+	},
+	PawnOnBackRank(Position),
+	/// A castling right is given without the king and rook on their start squares
+	CastlingWithoutPieces(char),
+	/// The side that just moved can't have left its king in check
+	OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::WrongFieldCount(count) => write!(f, "expected 6 fields, found {count}"),
+			Self::WrongRankCount(count) => write!(f, "expected 8 ranks, found {count}"),
+			Self::BadRankLength { rank, length } => {
+				write!(f, "rank {rank} covers {length} squares instead of 8")
+			}
+			Self::InvalidPieceSymbol(symbol) => write!(f, "invalid piece symbol '{symbol}'"),
+			Self::InvalidSideToMove(side) => write!(f, "invalid side to move '{side}'"),
+			Self::InvalidCastling(castling) => write!(f, "invalid castling rights '{castling}'"),
+			Self::InvalidEnPassant(square) => write!(f, "invalid en passant square '{square}'"),
+			Self::InvalidHalfmoveClock(clock) => write!(f, "invalid halfmove clock '{clock}'"),
+			Self::InvalidFullmoveNumber(number) => {
+				write!(f, "invalid fullmove number '{number}'")
+			}
+			Self::KingCount { color, count } => write!(f, "{color:?} has {count} kings"),
+			Self::PawnOnBackRank(position) => write!(f, "pawn on back rank square {position}"),
+			Self::CastlingWithoutPieces(right) => {
+				write!(f, "castling right '{right}' without king and rook in place")
+			}
+			Self::OpponentInCheck => write!(f, "the side not to move is in check"),
+		}
+	}
+}
+
+impl Error for FenError {}
+
+const fn piece_symbol(piece: Piece) -> char {
+	let symbol = piece.piece_type.symbol();
+	match piece.color {
+		PieceColor::White => symbol.to_ascii_uppercase(),
+		PieceColor::Black => symbol,
+	}
+}
+
+fn piece_from_symbol(symbol: char) -> Option<Piece> {
+	let piece_type = match symbol.to_ascii_lowercase() {
+		'k' => Pieces::King,
+		'q' => Pieces::Queen,
+		'b' => Pieces::Bishop,
+		'n' => Pieces::Knight,
+		'r' => Pieces::Rook,
+		'p' => Pieces::Pawn,
+		_ => return None,
+	};
+	let color = if symbol.is_ascii_uppercase() {
+		PieceColor::White
+	} else {
+		PieceColor::Black
+	};
+	Some(Piece::new(piece_type, color))
+}
+
+impl Board {
+	/// Parses all six fields of a FEN string and checks that the result is a position that can
+	/// actually be played from
+	pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+		let fields: Vec<&str> = fen.split_whitespace().collect();
+		let &[placement, side, castling, en_passant, halfmove, fullmove] = fields.as_slice() else {
+			return Err(FenError::WrongFieldCount(fields.len()));
+		};
+
+		let mut board = Self::empty();
+		board.parse_placement(placement)?;
+
+		board.side_to_move = match side {
+			"w" => PieceColor::White,
+			"b" => PieceColor::Black,
+			_ => return Err(FenError::InvalidSideToMove(side.to_string())),
+		};
+
+		board.castling_rights = board.parse_castling(castling)?;
+		board.en_passant = board.parse_en_passant(en_passant)?;
+
+		board.halfmove_clock = halfmove
+			.parse()
+			.map_err(|_| FenError::InvalidHalfmoveClock(halfmove.to_string()))?;
+		board.fullmove_number = fullmove
+			.parse()
+			.ok()
+			.filter(|&x| x > 0)
+			.ok_or_else(|| FenError::InvalidFullmoveNumber(fullmove.to_string()))?;
+
+		board.validate()?;
+		board.refresh_zobrist_key();
+		Ok(board)
+	}
+
+	fn parse_placement(&mut self, placement: &str) -> Result<(), FenError> {
+		let ranks: Vec<&str> = placement.split('/').collect();
+		if ranks.len() != BOARD_SIZE as usize {
+			return Err(FenError::WrongRankCount(ranks.len()));
+		}
+
+		for (row, rank) in (0..BOARD_SIZE).rev().zip(ranks) {
+			let mut col = 0;
+			for symbol in rank.chars() {
+				if let Some(empty_squares) = symbol.to_digit(10).filter(|x| (1..=8).contains(x)) {
+					col += empty_squares as usize;
+				} else {
+					let piece =
+						piece_from_symbol(symbol).ok_or(FenError::InvalidPieceSymbol(symbol))?;
+					if col < BOARD_SIZE as usize {
+						self.set_piece(Position::new(row, col as i8).index(), Some(piece));
+					}
+					col += 1;
+				}
+			}
+			if col != BOARD_SIZE as usize {
+				return Err(FenError::BadRankLength {
+					rank: row + 1,
+					length: col,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	fn parse_castling(&self, castling: &str) -> Result<CastlingRights, FenError> {
+		let mut castling_rights = CastlingRights::default();
+		if castling == "-" {
+			return Ok(castling_rights);
+		}
+
+		let invalid = || FenError::InvalidCastling(castling.to_string());
+		for symbol in castling.chars() {
+			let (right, color, rook_col) = match symbol {
+				'K' => (&mut castling_rights.white_kingside, PieceColor::White, 7),
+				'Q' => (&mut castling_rights.white_queenside, PieceColor::White, 0),
+				'k' => (&mut castling_rights.black_kingside, PieceColor::Black, 7),
+				'q' => (&mut castling_rights.black_queenside, PieceColor::Black, 0),
+				_ => return Err(invalid()),
+			};
+			if *right {
+				return Err(invalid());
+			}
+			*right = true;
+
+			let row = if color == PieceColor::White {
+				0
+			} else {
+				BOARD_SIZE - 1
+			};
+			let king = self.piece_at(Position::new(row, 4).index());
+			let rook = self.piece_at(Position::new(row, rook_col).index());
+			if king != Some(Piece::new(Pieces::King, color))
+				|| rook != Some(Piece::new(Pieces::Rook, color))
+			{
+				return Err(FenError::CastlingWithoutPieces(symbol));
+			}
+		}
+		Ok(castling_rights)
+	}
+
+	fn parse_en_passant(&self, en_passant: &str) -> Result<Option<i8>, FenError> {
+		if en_passant == "-" {
+			return Ok(None);
+		}
+
+		let invalid = || FenError::InvalidEnPassant(en_passant.to_string());
+		let target = en_passant.parse::<Position>().map_err(|_| invalid())?;
+
+		// The square has to be the one right behind a pawn of the side that just moved, which
+		// came from the square right in front of it
+		let (target_row, direction) = match self.side_to_move {
+			PieceColor::White => (5, -1),
+			PieceColor::Black => (2, 1),
+		};
+		let pawn = Position::new(target.row + direction, target.col);
+		let start = Position::new(target.row - direction, target.col);
+		if target.row != target_row
+			|| self.piece_at(target.index()).is_some()
+			|| self.piece_at(start.index()).is_some()
+			|| self.piece_at(pawn.index())
+				!= Some(Piece::new(Pieces::Pawn, self.side_to_move.not()))
+		{
+			return Err(invalid());
+		}
+		Ok(Some(target.index()))
+	}
+
+	fn validate(&self) -> Result<(), FenError> {
+		for color in [PieceColor::White, PieceColor::Black] {
+			let count = self
+				.pieces()
+				.filter(|(_, x)| *x == Piece::new(Pieces::King, color))
+				.count();
+			if count != 1 {
+				return Err(FenError::KingCount { color, count });
+			}
+		}
+
+		if let Some((square, _)) = self.pieces().find(|(square, x)| {
+			let row = Position::from_index(*square).row;
+			x.piece_type == Pieces::Pawn && (row == 0 || row == BOARD_SIZE - 1)
+		}) {
+			return Err(FenError::PawnOnBackRank(Position::from_index(square)));
+		}
+
+		if self.is_in_check(self.side_to_move.not()) {
+			return Err(FenError::OpponentInCheck);
+		}
+		Ok(())
+	}
+
+	/// Writes the position as a FEN string, which [`Board::from_fen`] reads back into the same
+	/// board
+	pub fn to_fen(&self) -> String {
+		let mut placement = Vec::new();
+		for row in (0..BOARD_SIZE).rev() {
+			let mut rank = String::new();
+			let mut empty_squares = 0;
+			for col in 0..BOARD_SIZE {
+				if let Some(piece) = self.piece_at(Position::new(row, col).index()) {
+					if empty_squares > 0 {
+						rank.push_str(&empty_squares.to_string());
+						empty_squares = 0;
+					}
+					rank.push(piece_symbol(piece));
+				} else {
+					empty_squares += 1;
+				}
+			}
+			if empty_squares > 0 {
+				rank.push_str(&empty_squares.to_string());
+			}
+			placement.push(rank);
+		}
+
+		let side = match self.side_to_move {
+			PieceColor::White => "w",
+			PieceColor::Black => "b",
+		};
+		let en_passant = self
+			.en_passant
+			.map_or_else(|| "-".to_string(), |x| Position::from_index(x).to_string());
+
+		format!(
+			"{} {side} {} {en_passant} {} {}",
+			placement.join("/"),
+			self.castling_rights,
+			self.halfmove_clock,
+			self.fullmove_number
+		)
+	}
+}
+
+impl FromStr for Board {
+	type Err = FenError;
+
+	fn from_str(fen: &str) -> Result<Self, Self::Err> {
+		Self::from_fen(fen)
+	}
+}
+
+impl fmt::Display for CastlingRights {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let rights = [
+			(self.white_kingside, 'K'),
+			(self.white_queenside, 'Q'),
+			(self.black_kingside, 'k'),
+			(self.black_queenside, 'q'),
+		];
+		let symbols: String = rights
+			.into_iter()
+			.filter_map(|(allowed, symbol)| allowed.then_some(symbol))
+			.collect();
+
+		if symbols.is_empty() {
+			write!(f, "-")
+		} else {
+			write!(f, "{symbols}")
+		}
+	}
+}
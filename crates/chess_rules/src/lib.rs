@@ -0,0 +1,44 @@
+//! Rules of chess without any rendering or windowing attached: bitboard board state, move
+//! generation, Zobrist keys, game end detection, FEN, SAN, UCI move and PGN reading and writing,
+//! time controls, perft and an alpha-beta search to play against.
+
+mod attacks;
+mod bitboard;
+mod board;
+mod eval;
+mod eval_params;
+mod fen;
+mod movegen;
+mod moves;
+mod outcome;
+mod perft;
+mod pgn;
+mod piece;
+mod position;
+mod san;
+mod search;
+mod skill;
+mod time_control;
+mod uci;
+mod zobrist;
+
+pub use attacks::{
+	bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+};
+pub use bitboard::Bitboard;
+pub use board::{Board, CastlingRights};
+pub use eval_params::{EvalParams, EvalParamsError, EvalParamsErrorKind, Weight};
+pub use fen::FenError;
+pub use movegen::{MoveList, MAX_MOVES};
+pub use moves::{Move, MoveFlag, UndoInfo};
+pub use outcome::{GameOverReason, GameResult};
+pub use pgn::{PgnError, PgnErrorKind, PgnGame, PgnMove};
+pub use piece::{Piece, PieceColor, Pieces};
+pub use position::{ParsePositionError, Position};
+pub use san::{San, SanError};
+pub use search::{Score, SearchInfo, SearchLimits, Searcher};
+pub use skill::SkillLevel;
+pub use time_control::{ParseTimeControlError, TimeBonus, TimeControl, TimeControlStage};
+pub use uci::{CastlingMode, UciMove, UciMoveError};
+
+pub const BOARD_SIZE: i8 = 8;
@@ -0,0 +1,216 @@
+use std::{fmt, ops::Deref};
+
+use crate::{
+	attacks::{
+		bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+	},
+	Bitboard, Board, Move, MoveFlag, Piece, PieceColor, Pieces, BOARD_SIZE,
+};
+
+const PROMOTION_PIECES: [Pieces; 4] = [Pieces::Queen, Pieces::Rook, Pieces::Bishop, Pieces::Knight];
+
+/// More moves than any reachable position has, even before dropping the illegal ones
+pub const MAX_MOVES: usize = 256;
+
+/// Moves stored in place on the stack, so generating them never allocates
+#[derive(Clone)]
+pub struct MoveList {
+	moves: [Move; MAX_MOVES],
+	len: usize,
+}
+
+impl MoveList {
+	pub const fn new() -> Self {
+		const PLACEHOLDER: Move = Move::new(0, 0, Piece::new(Pieces::Pawn, PieceColor::White));
+		Self {
+			moves: [PLACEHOLDER; MAX_MOVES],
+			len: 0,
+		}
+	}
+
+	pub fn push(&mut self, mv: Move) {
+		self.moves[self.len] = mv;
+		self.len += 1;
+	}
+
+	/// Keeps only the moves `keep` returns `true` for, in their original order
+	pub fn retain(&mut self, mut keep: impl FnMut(Move) -> bool) {
+		let mut kept = 0;
+		for index in 0..self.len {
+			let mv = self.moves[index];
+			if keep(mv) {
+				self.moves[kept] = mv;
+				kept += 1;
+			}
+		}
+		self.len = kept;
+	}
+}
+
+impl Default for MoveList {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Deref for MoveList {
+	type Target = [Move];
+
+	fn deref(&self) -> &[Move] {
+		&self.moves[..self.len]
+	}
+}
+
+impl fmt::Debug for MoveList {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
+
+impl IntoIterator for MoveList {
+	type Item = Move;
+	type IntoIter = std::iter::Take<std::array::IntoIter<Move, MAX_MOVES>>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.moves.into_iter().take(self.len)
+	}
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+	type Item = &'a Move;
+	type IntoIter = std::slice::Iter<'a, Move>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+/// Generates the moves of the side to move's pieces on `from_squares`, without checking whether
+/// they leave the own king in check
+pub fn pseudo_legal_moves(board: &Board, from_squares: Bitboard, moves: &mut MoveList) {
+	let color = board.side_to_move;
+	let own = board.color_bitboard(color);
+	let occupied = board.occupied();
+
+	for from in (own & from_squares).squares() {
+		let Some(piece) = board.piece_at(from) else {
+			continue;
+		};
+		let attacks = match piece.piece_type {
+			Pieces::Pawn => {
+				pawn_moves(board, from, piece, moves);
+				continue;
+			}
+			Pieces::King => {
+				castling_moves(board, from, piece, moves);
+				king_attacks(from)
+			}
+			Pieces::Knight => knight_attacks(from),
+			Pieces::Bishop => bishop_attacks(from, occupied),
+			Pieces::Rook => rook_attacks(from, occupied),
+			Pieces::Queen => queen_attacks(from, occupied),
+		};
+
+		for to in (attacks & !own).squares() {
+			moves.push(Move::new(from, to, piece).with_capture(board.piece_at(to)));
+		}
+	}
+}
+
+fn pawn_moves(board: &Board, from: i8, piece: Piece, moves: &mut MoveList) {
+	let (forward, start_row) = if piece.color == PieceColor::White {
+		(BOARD_SIZE, 1)
+	} else {
+		(-BOARD_SIZE, BOARD_SIZE - 2)
+	};
+
+	// Pushes are only possible onto empty squares, the double push only from the start row. A pawn
+	// never stands on the last rank, so the square in front of it is always on the board
+	let one_step = from + forward;
+	if board.piece_at(one_step).is_none() {
+		push_pawn_move(Move::new(from, one_step, piece), moves);
+
+		let two_step = one_step + forward;
+		if from / BOARD_SIZE == start_row && board.piece_at(two_step).is_none() {
+			moves.push(Move::new(from, two_step, piece).with_flag(MoveFlag::DoublePush));
+		}
+	}
+
+	let enemies = board.color_bitboard(piece.color.not());
+	for to in pawn_attacks(piece.color, from).squares() {
+		if enemies.contains(to) {
+			push_pawn_move(
+				Move::new(from, to, piece).with_capture(board.piece_at(to)),
+				moves,
+			);
+		} else if board.en_passant == Some(to) {
+			let captured_pawn = Piece::new(Pieces::Pawn, piece.color.not());
+			moves.push(
+				Move::new(from, to, piece)
+					.with_capture(Some(captured_pawn))
+					.with_flag(MoveFlag::EnPassant),
+			);
+		}
+	}
+}
+
+/// Pawns reaching the last rank have to turn into one of the promotion pieces
+fn push_pawn_move(mv: Move, moves: &mut MoveList) {
+	let target_row = mv.to / BOARD_SIZE;
+	if target_row == 0 || target_row == BOARD_SIZE - 1 {
+		for piece_type in PROMOTION_PIECES {
+			moves.push(mv.with_promotion(piece_type));
+		}
+	} else {
+		moves.push(mv);
+	}
+}
+
+fn castling_moves(board: &Board, from: i8, piece: Piece, moves: &mut MoveList) {
+	let (kingside, queenside) = board.castling_rights.for_color(piece.color);
+	let row_start = from / BOARD_SIZE * BOARD_SIZE;
+	let home_square = row_start + 4;
+	let enemy = piece.color.not();
+
+	if from != home_square || is_square_attacked(board, home_square, enemy) {
+		return;
+	}
+
+	for (allowed, rook_col, step) in [(kingside, 7, 1), (queenside, 0, -1)] {
+		let rook_square = row_start + rook_col;
+		let has_rook = board.piece_at(rook_square) == Some(Piece::new(Pieces::Rook, piece.color));
+		if !allowed || !has_rook {
+			continue;
+		}
+
+		// Every square between the king and the rook has to be empty
+		let mut between = (home_square + step..rook_square).chain(rook_square + 1..home_square);
+		if between.any(|square| board.piece_at(square).is_some()) {
+			continue;
+		}
+
+		// The king may not pass through or land on an attacked square
+		let passes_attack = [home_square + step, home_square + step * 2]
+			.into_iter()
+			.any(|square| is_square_attacked(board, square, enemy));
+		if !passes_attack {
+			moves.push(Move::new(from, home_square + step * 2, piece).with_flag(MoveFlag::Castle));
+		}
+	}
+}
+
+/// Looks from `square` outwards with every kind of piece: whatever it would hit of the attacker's
+/// own kind attacks the square
+pub fn is_square_attacked(board: &Board, square: i8, attacker_color: PieceColor) -> bool {
+	let occupied = board.occupied();
+	let pieces = |piece_type| board.bitboard(Piece::new(piece_type, attacker_color));
+	let diagonal = pieces(Pieces::Bishop) | pieces(Pieces::Queen);
+	let straight = pieces(Pieces::Rook) | pieces(Pieces::Queen);
+
+	let attackers = (pawn_attacks(attacker_color.not(), square) & pieces(Pieces::Pawn))
+		| (knight_attacks(square) & pieces(Pieces::Knight))
+		| (king_attacks(square) & pieces(Pieces::King))
+		| (bishop_attacks(square, occupied) & diagonal)
+		| (rook_attacks(square, occupied) & straight);
+	!attackers.is_empty()
+}
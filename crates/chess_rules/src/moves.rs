@@ -0,0 +1,108 @@
+use std::fmt;
+
+use crate::{CastlingRights, Piece, Pieces, Position, BOARD_SIZE};
+
+/// What kind of special move a [`Move`] is, if any
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveFlag {
+	#[default]
+	Normal,
+	/// A pawn moving two squares from its start row, which allows an en passant reply
+	DoublePush,
+	/// A pawn capturing a pawn that just double pushed past it
+	EnPassant,
+	/// A king moving two squares towards one of its rooks, which jumps over it
+	Castle,
+}
+
+/// A fully described move: which piece goes where, what it takes and what it turns into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+	pub from: i8,
+	pub to: i8,
+	pub piece: Piece,
+	pub captured: Option<Piece>,
+	pub promotion: Option<Pieces>,
+	pub flag: MoveFlag,
+}
+
+impl Move {
+	pub const fn new(from: i8, to: i8, piece: Piece) -> Self {
+		Self {
+			from,
+			to,
+			piece,
+			captured: None,
+			promotion: None,
+			flag: MoveFlag::Normal,
+		}
+	}
+
+	pub const fn with_capture(self, captured: Option<Piece>) -> Self {
+		Self { captured, ..self }
+	}
+
+	pub const fn with_promotion(self, piece_type: Pieces) -> Self {
+		Self {
+			promotion: Some(piece_type),
+			..self
+		}
+	}
+
+	pub const fn with_flag(self, flag: MoveFlag) -> Self {
+		Self { flag, ..self }
+	}
+
+	pub const fn is_capture(self) -> bool {
+		self.captured.is_some()
+	}
+
+	/// The rook's start and target square when this is a castling move
+	pub const fn castling_rook_squares(self) -> Option<(i8, i8)> {
+		if !matches!(self.flag, MoveFlag::Castle) {
+			return None;
+		}
+
+		let row_start = self.from / BOARD_SIZE * BOARD_SIZE;
+		if self.to > self.from {
+			Some((row_start + 7, row_start + 5))
+		} else {
+			Some((row_start, row_start + 3))
+		}
+	}
+
+	/// The square of the pawn taken when this is an en passant capture
+	pub const fn en_passant_capture_square(self) -> Option<i8> {
+		if matches!(self.flag, MoveFlag::EnPassant) {
+			Some(self.from / BOARD_SIZE * BOARD_SIZE + self.to % BOARD_SIZE)
+		} else {
+			None
+		}
+	}
+}
+
+/// Writes the move in long algebraic notation, like `e2e4` or `e7e8q`
+impl fmt::Display for Move {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}{}",
+			Position::from_index(self.from),
+			Position::from_index(self.to)
+		)?;
+		if let Some(piece_type) = self.promotion {
+			write!(f, "{}", piece_type.symbol())?;
+		}
+		Ok(())
+	}
+}
+
+/// The parts of the board state a move throws away, which
+/// [`Board::unmake_move`](crate::Board::unmake_move) needs to get them back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoInfo {
+	pub castling_rights: CastlingRights,
+	pub en_passant: Option<i8>,
+	pub halfmove_clock: u32,
+	pub zobrist_key: u64,
+}
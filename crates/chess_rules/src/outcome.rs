@@ -0,0 +1,48 @@
+use strum::{Display, EnumString};
+
+use crate::PieceColor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+pub enum GameResult {
+	#[strum(serialize = "1-0")]
+	WhiteWins,
+	#[strum(serialize = "0-1")]
+	BlackWins,
+	#[strum(serialize = "1/2-1/2")]
+	Draw,
+}
+
+impl GameResult {
+	pub const fn win_for(color: PieceColor) -> Self {
+		match color {
+			PieceColor::White => Self::WhiteWins,
+			PieceColor::Black => Self::BlackWins,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum GameOverReason {
+	#[strum(serialize = "checkmate")]
+	Checkmate,
+	#[strum(serialize = "stalemate")]
+	Stalemate,
+	#[strum(serialize = "fifty-move rule")]
+	FiftyMoveRule,
+	#[strum(serialize = "threefold repetition")]
+	ThreefoldRepetition,
+	#[strum(serialize = "insufficient material")]
+	InsufficientMaterial,
+	#[strum(serialize = "time forfeit")]
+	Timeout,
+	#[strum(serialize = "resignation")]
+	Resignation,
+	#[strum(serialize = "agreement")]
+	Agreement,
+	/// A player stopped taking part, like an engine that crashed or stopped answering
+	#[strum(serialize = "abandonment")]
+	Abandoned,
+	/// The flag fell, but the opponent couldn't have won anyway
+	#[strum(serialize = "timeout vs insufficient material")]
+	TimeoutVsInsufficientMaterial,
+}
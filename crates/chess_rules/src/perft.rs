@@ -0,0 +1,47 @@
+use crate::{Board, Move};
+
+impl Board {
+	/// Counts the leaf nodes of the move tree `depth` plies deep. Comparing the count against
+	/// published values is the standard way to find move generation bugs
+	pub fn perft(&self, depth: u32) -> u64 {
+		self.clone().count_nodes(depth)
+	}
+
+	/// [`Board::perft`] split up by the first move, which narrows a wrong count down to the move
+	/// it goes wrong after
+	pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+		let mut board = self.clone();
+		board
+			.legal_moves()
+			.into_iter()
+			.map(|mv| {
+				let undo_info = board.make_move(mv);
+				let nodes = board.count_nodes(depth.saturating_sub(1));
+				board.unmake_move(mv, undo_info);
+				(mv, nodes)
+			})
+			.collect()
+	}
+
+	fn count_nodes(&mut self, depth: u32) -> u64 {
+		if depth == 0 {
+			return 1;
+		}
+
+		let moves = self.legal_moves();
+		// The moves of the last ply don't need to be played to be counted
+		if depth == 1 {
+			return moves.len() as u64;
+		}
+
+		moves
+			.into_iter()
+			.map(|mv| {
+				let undo_info = self.make_move(mv);
+				let nodes = self.count_nodes(depth - 1);
+				self.unmake_move(mv, undo_info);
+				nodes
+			})
+			.sum()
+	}
+}
@@ -0,0 +1,568 @@
+//! Reading and writing games in Portable Game Notation
+
+use std::{error::Error, fmt, time::Duration};
+
+use crate::{Board, FenError, GameResult, Move, PieceColor, SanError};
+
+/// Tags every PGN game has, in the order they have to come in
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Movetext lines are kept below 80 characters as the standard asks for
+const MAX_LINE_LENGTH: usize = 79;
+
+/// One move of a game together with the annotations that follow it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+	pub mv: Move,
+	/// Numeric annotation glyphs, `$1` (or `!`) to `$139`
+	pub nags: Vec<u8>,
+	pub comment: Option<String>,
+	/// Comment written before the move. Only the first move of a variation has one, the main line
+	/// keeps it in [`PgnGame::comment`]
+	pub leading_comment: Option<String>,
+	/// Lines that could have been played instead of this move
+	pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+	pub const fn new(mv: Move) -> Self {
+		Self {
+			mv,
+			nags: Vec::new(),
+			comment: None,
+			leading_comment: None,
+			variations: Vec::new(),
+		}
+	}
+
+	/// Time left on the mover's clock from a `[%clk h:mm:ss]` command in the comment
+	pub fn clock(&self) -> Option<Duration> {
+		let comment = self.comment.as_deref()?;
+		let start = comment.find("[%clk ")? + "[%clk ".len();
+		let end = start + comment[start..].find(']')?;
+
+		let mut seconds = 0.;
+		for part in comment[start..end].trim().split(':') {
+			seconds = seconds * 60. + part.parse::<f64>().ok()?;
+		}
+		Duration::try_from_secs_f64(seconds).ok()
+	}
+
+	/// Adds a `[%clk h:mm:ss]` command to the comment, replacing the one already there
+	pub fn set_clock(&mut self, remaining: Duration) {
+		let seconds = remaining.as_secs();
+		let clock = format!(
+			"[%clk {}:{:02}:{:02}]",
+			seconds / 3600,
+			seconds / 60 % 60,
+			seconds % 60
+		);
+
+		let comment = self.comment.take().unwrap_or_default();
+		let without_clock = match comment.find("[%clk ") {
+			Some(start) => {
+				let end = comment[start..]
+					.find(']')
+					.map_or(comment.len(), |x| start + x + 1);
+				format!("{}{}", &comment[..start], &comment[end..])
+			}
+			None => comment,
+		};
+		let without_clock = without_clock.trim();
+
+		self.comment = Some(if without_clock.is_empty() {
+			clock
+		} else {
+			format!("{clock} {without_clock}")
+		});
+	}
+}
+
+/// A game with its tag pairs, the position it started from and its moves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+	/// Tag pairs in the order they are written, the Seven Tag Roster first
+	pub tags: Vec<(String, String)>,
+	pub start: Board,
+	/// Comment before the first move
+	pub comment: Option<String>,
+	pub moves: Vec<PgnMove>,
+}
+
+impl Default for PgnGame {
+	fn default() -> Self {
+		Self::new(Board::starting_position())
+	}
+}
+
+impl PgnGame {
+	/// An empty game with the Seven Tag Roster filled with unknown values
+	pub fn new(start: Board) -> Self {
+		let mut game = Self {
+			tags: Vec::new(),
+			start,
+			comment: None,
+			moves: Vec::new(),
+		};
+		for name in SEVEN_TAG_ROSTER {
+			let value = match name {
+				"Date" => "????.??.??",
+				"Result" => "*",
+				_ => "?",
+			};
+			game.set_tag(name, value);
+		}
+		if game.start != Board::starting_position() {
+			game.set_tag("SetUp", "1");
+			game.set_tag("FEN", &game.start.to_fen());
+		}
+		game
+	}
+
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|(x, _)| x == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub fn set_tag(&mut self, name: &str, value: &str) {
+		if let Some((_, old)) = self.tags.iter_mut().find(|(x, _)| x == name) {
+			*old = value.to_string();
+		} else {
+			self.tags.push((name.to_string(), value.to_string()));
+		}
+	}
+
+	/// `None` while the game is still going on, which PGN writes as `*`
+	pub fn result(&self) -> Option<GameResult> {
+		self.tag("Result")?.parse().ok()
+	}
+
+	pub fn set_result(&mut self, result: Option<GameResult>) {
+		let value = result.map_or_else(|| "*".to_string(), |x| x.to_string());
+		self.set_tag("Result", &value);
+	}
+
+	/// The position after the last move of the main line
+	pub fn final_board(&self) -> Board {
+		let mut board = self.start.clone();
+		for played in &self.moves {
+			board.make_move(played.mv);
+		}
+		board
+	}
+
+	/// Reads every game in `pgn`
+	pub fn parse_all(pgn: &str) -> Result<Vec<Self>, PgnError> {
+		let tokens = tokenize(pgn).map_err(|kind| PgnError { game: 1, kind })?;
+
+		let mut games = Vec::new();
+		let mut rest = tokens.as_slice();
+		while !rest.is_empty() {
+			let game_number = games.len() + 1;
+			let (game, remaining) = parse_game(rest).map_err(|kind| PgnError {
+				game: game_number,
+				kind,
+			})?;
+			games.push(game);
+			rest = remaining;
+		}
+		Ok(games)
+	}
+}
+
+/// Writes the game as PGN, ready to be saved to a file
+impl fmt::Display for PgnGame {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let roster = SEVEN_TAG_ROSTER
+			.iter()
+			.map(|&name| (name, self.tag(name).unwrap_or("?")));
+		let others = self
+			.tags
+			.iter()
+			.filter(|(name, _)| !SEVEN_TAG_ROSTER.contains(&name.as_str()))
+			.map(|(name, value)| (name.as_str(), value.as_str()));
+		for (name, value) in roster.chain(others) {
+			let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+			writeln!(f, "[{name} \"{value}\"]")?;
+		}
+		writeln!(f)?;
+
+		let mut words = Vec::new();
+		if let Some(comment) = &self.comment {
+			words.push(format!("{{{comment}}}"));
+		}
+		write_line(&mut words, &self.start, &self.moves);
+		words.push(self.tag("Result").unwrap_or("*").to_string());
+
+		let mut line = String::new();
+		for word in words {
+			if !line.is_empty() && line.len() + 1 + word.len() > MAX_LINE_LENGTH {
+				writeln!(f, "{line}")?;
+				line.clear();
+			}
+			if !line.is_empty() {
+				line.push(' ');
+			}
+			line.push_str(&word);
+		}
+		writeln!(f, "{line}")
+	}
+}
+
+/// Turns a line of moves into movetext words. Move numbers are written before every white move,
+/// and before a black move when something came between it and the white move
+fn write_line(words: &mut Vec<String>, start: &Board, moves: &[PgnMove]) {
+	let mut board = start.clone();
+	let mut needs_number = true;
+
+	for played in moves {
+		if let Some(comment) = &played.leading_comment {
+			words.push(format!("{{{comment}}}"));
+			needs_number = true;
+		}
+		let san = board.san(played.mv);
+		let number = board.fullmove_number;
+		if board.side_to_move == PieceColor::White {
+			words.push(format!("{number}. {san}"));
+		} else if needs_number {
+			words.push(format!("{number}... {san}"));
+		} else {
+			words.push(san.to_string());
+		}
+		needs_number = false;
+
+		for nag in &played.nags {
+			words.push(format!("${nag}"));
+		}
+		if let Some(comment) = &played.comment {
+			words.push(format!("{{{comment}}}"));
+			needs_number = true;
+		}
+		for variation in &played.variations {
+			let mut variation_words = Vec::new();
+			write_line(&mut variation_words, &board, variation);
+			if let Some(first) = variation_words.first_mut() {
+				first.insert(0, '(');
+			}
+			if let Some(last) = variation_words.last_mut() {
+				last.push(')');
+			}
+			words.extend(variation_words);
+			needs_number = true;
+		}
+
+		board.make_move(played.mv);
+	}
+}
+
+/// What went wrong reading a PGN file, and in which of its games
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnError {
+	/// Number of the game in the file, counted from 1
+	pub game: usize,
+	pub kind: PgnErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnErrorKind {
+	UnexpectedCharacter(char),
+	UnterminatedComment,
+	InvalidTag,
+	InvalidFen(FenError),
+	/// A move of the main line or a variation that can't be played, with its number in the line
+	InvalidMove {
+		ply: usize,
+		error: SanError,
+	},
+	/// A `(` or `)` without its counterpart
+	UnbalancedVariation,
+	/// A variation has to come after the move it replaces
+	VariationWithoutMove,
+}
+
+impl fmt::Display for PgnError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "game {}: ", self.game)?;
+		match &self.kind {
+			PgnErrorKind::UnexpectedCharacter(character) => {
+				write!(f, "unexpected character '{character}'")
+			}
+			PgnErrorKind::UnterminatedComment => write!(f, "comment is never closed"),
+			PgnErrorKind::InvalidTag => write!(f, "invalid tag pair"),
+			PgnErrorKind::InvalidFen(error) => write!(f, "invalid FEN tag: {error}"),
+			PgnErrorKind::InvalidMove { ply, error } => write!(f, "ply {ply}: {error}"),
+			PgnErrorKind::UnbalancedVariation => write!(f, "unbalanced parentheses"),
+			PgnErrorKind::VariationWithoutMove => write!(f, "variation before the first move"),
+		}
+	}
+}
+
+impl Error for PgnError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	Tag(String, String),
+	Comment(String),
+	Nag(u8),
+	San(String),
+	StartVariation,
+	EndVariation,
+	Result(Option<GameResult>),
+}
+
+/// Move suffixes that are short for one of the first six annotation glyphs
+fn suffix_nag(suffix: &str) -> Option<u8> {
+	match suffix {
+		"!" => Some(1),
+		"?" => Some(2),
+		"!!" => Some(3),
+		"??" => Some(4),
+		"!?" => Some(5),
+		"?!" => Some(6),
+		_ => None,
+	}
+}
+
+fn tokenize(pgn: &str) -> Result<Vec<Token>, PgnErrorKind> {
+	let mut tokens = Vec::new();
+	let mut chars = pgn.chars().peekable();
+	let mut line_start = true;
+
+	while let Some(character) = chars.next() {
+		let at_line_start = line_start;
+		line_start = character == '\n';
+
+		match character {
+			// A `%` in the first column escapes the rest of the line
+			'%' if at_line_start => {
+				chars.by_ref().find(|&x| x == '\n');
+				line_start = true;
+			}
+			';' => {
+				let comment: String = chars.by_ref().take_while(|&x| x != '\n').collect();
+				tokens.push(Token::Comment(comment.trim().to_string()));
+				line_start = true;
+			}
+			'{' => {
+				let mut comment = String::new();
+				loop {
+					match chars.next() {
+						Some('}') => break,
+						Some(x) => comment.push(x),
+						None => return Err(PgnErrorKind::UnterminatedComment),
+					}
+				}
+				tokens.push(Token::Comment(
+					comment.split_whitespace().collect::<Vec<_>>().join(" "),
+				));
+			}
+			'[' => tokens.push(tokenize_tag(&mut chars)?),
+			'(' => tokens.push(Token::StartVariation),
+			')' => tokens.push(Token::EndVariation),
+			'*' => tokens.push(Token::Result(None)),
+			'$' => {
+				let mut digits = String::new();
+				while let Some(x) = chars.next_if(char::is_ascii_digit) {
+					digits.push(x);
+				}
+				let nag = digits
+					.parse()
+					.map_err(|_| PgnErrorKind::UnexpectedCharacter('$'))?;
+				tokens.push(Token::Nag(nag));
+			}
+			'!' | '?' => {
+				let mut suffix = character.to_string();
+				while let Some(x) = chars.next_if(|x| matches!(x, '!' | '?')) {
+					suffix.push(x);
+				}
+				let nag =
+					suffix_nag(&suffix).ok_or(PgnErrorKind::UnexpectedCharacter(character))?;
+				tokens.push(Token::Nag(nag));
+			}
+			'.' => {}
+			x if x.is_whitespace() => {}
+			x if x.is_ascii_alphanumeric() => {
+				let mut symbol = x.to_string();
+				while let Some(x) =
+					chars.next_if(|x| x.is_ascii_alphanumeric() || "_+#=:-/".contains(*x))
+				{
+					symbol.push(x);
+				}
+
+				if let Ok(result) = symbol.parse::<GameResult>() {
+					tokens.push(Token::Result(Some(result)));
+				} else if !symbol.chars().all(|x| x.is_ascii_digit()) {
+					// Move numbers carry no information, the position already knows them
+					tokens.push(Token::San(symbol));
+				}
+			}
+			x => return Err(PgnErrorKind::UnexpectedCharacter(x)),
+		}
+	}
+	Ok(tokens)
+}
+
+fn tokenize_tag(
+	chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+) -> Result<Token, PgnErrorKind> {
+	while chars.next_if(|x| x.is_whitespace()).is_some() {}
+	let mut name = String::new();
+	while let Some(x) = chars.next_if(|x| x.is_ascii_alphanumeric() || *x == '_') {
+		name.push(x);
+	}
+	while chars.next_if(|x| x.is_whitespace()).is_some() {}
+
+	if name.is_empty() || chars.next() != Some('"') {
+		return Err(PgnErrorKind::InvalidTag);
+	}
+	let mut value = String::new();
+	loop {
+		match chars.next() {
+			Some('\\') => value.push(chars.next().ok_or(PgnErrorKind::InvalidTag)?),
+			Some('"') => break,
+			Some(x) => value.push(x),
+			None => return Err(PgnErrorKind::InvalidTag),
+		}
+	}
+
+	while chars.next_if(|x| x.is_whitespace()).is_some() {}
+	if chars.next() != Some(']') {
+		return Err(PgnErrorKind::InvalidTag);
+	}
+	Ok(Token::Tag(name, value))
+}
+
+/// Reads one game from the front of `tokens` and returns the tokens after it. A game ends with
+/// its result, or where the tags of the next game start
+fn parse_game(tokens: &[Token]) -> Result<(PgnGame, &[Token]), PgnErrorKind> {
+	let tag_count = tokens
+		.iter()
+		.take_while(|x| matches!(x, Token::Tag(..)))
+		.count();
+	let (tags, movetext) = tokens.split_at(tag_count);
+
+	let mut depth = 0;
+	let mut movetext_end = movetext.len();
+	let mut rest_start = movetext.len();
+	let mut result = None;
+	for (index, token) in movetext.iter().enumerate() {
+		match token {
+			Token::StartVariation => depth += 1,
+			Token::EndVariation => depth -= 1,
+			Token::Result(x) if depth == 0 => {
+				result = *x;
+				movetext_end = index;
+				rest_start = index + 1;
+				break;
+			}
+			Token::Tag(..) if depth == 0 => {
+				movetext_end = index;
+				rest_start = index;
+				break;
+			}
+			_ => {}
+		}
+	}
+	let rest = &movetext[rest_start..];
+	let movetext = &movetext[..movetext_end];
+
+	// Tags missing from the roster stay unknown
+	let mut game = PgnGame::default();
+	for token in tags {
+		if let Token::Tag(name, value) = token {
+			game.set_tag(name, value);
+		}
+	}
+	if let Some(fen) = game.tag("FEN") {
+		game.start = Board::from_fen(fen).map_err(PgnErrorKind::InvalidFen)?;
+	}
+	if !tags.iter().any(|x| matches!(x, Token::Tag(name, _) if name == "Result")) {
+		game.set_result(result);
+	}
+
+	let mut parser = LineParser {
+		tokens: movetext,
+		index: 0,
+	};
+	let (comment, moves) = parser.line(game.start.clone(), false)?;
+	game.comment = comment;
+	game.moves = moves;
+
+	Ok((game, rest))
+}
+
+struct LineParser<'a> {
+	tokens: &'a [Token],
+	index: usize,
+}
+
+impl LineParser<'_> {
+	/// Reads moves until the end of the movetext, or until the `)` closing a variation. Returns
+	/// the comment before the first move as well
+	fn line(
+		&mut self,
+		mut board: Board,
+		is_variation: bool,
+	) -> Result<(Option<String>, Vec<PgnMove>), PgnErrorKind> {
+		let mut leading_comment: Option<String> = None;
+		let mut moves: Vec<PgnMove> = Vec::new();
+		// Variations replace the last move, so they start from the position before it
+		let mut previous_board = board.clone();
+
+		while let Some(token) = self.tokens.get(self.index) {
+			self.index += 1;
+			match token {
+				Token::San(san) => {
+					let mv = board
+						.parse_san(san)
+						.map_err(|error| PgnErrorKind::InvalidMove {
+							ply: moves.len() + 1,
+							error,
+						})?;
+					previous_board = board.clone();
+					board.make_move(mv);
+					moves.push(PgnMove::new(mv));
+				}
+				Token::Nag(nag) => {
+					if let Some(last) = moves.last_mut() {
+						last.nags.push(*nag);
+					}
+				}
+				Token::Comment(comment) => {
+					let target = match moves.last_mut() {
+						Some(last) => &mut last.comment,
+						None => &mut leading_comment,
+					};
+					*target = Some(match target.take() {
+						Some(existing) => format!("{existing} {comment}"),
+						None => comment.clone(),
+					});
+				}
+				Token::StartVariation => {
+					if moves.is_empty() {
+						return Err(PgnErrorKind::VariationWithoutMove);
+					}
+					let (comment, mut variation) = self.line(previous_board.clone(), true)?;
+					if let Some(first) = variation.first_mut() {
+						first.leading_comment = comment;
+					}
+					if let Some(last) = moves.last_mut() {
+						last.variations.push(variation);
+					}
+				}
+				Token::EndVariation if is_variation => return Ok((leading_comment, moves)),
+				Token::EndVariation => return Err(PgnErrorKind::UnbalancedVariation),
+				// Results inside variations happen in the wild and mean nothing
+				Token::Result(_) | Token::Tag(..) => {}
+			}
+		}
+
+		if is_variation {
+			Err(PgnErrorKind::UnbalancedVariation)
+		} else {
+			Ok((leading_comment, moves))
+		}
+	}
+}
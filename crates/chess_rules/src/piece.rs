@@ -0,0 +1,53 @@
+use strum::{Display, EnumIter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum Pieces {
+	King,
+	Queen,
+	Bishop,
+	Knight,
+	Rook,
+	Pawn,
+}
+
+impl Pieces {
+	/// Lowercase letter of the piece as used in FEN and long algebraic notation
+	pub const fn symbol(self) -> char {
+		match self {
+			Self::King => 'k',
+			Self::Queen => 'q',
+			Self::Bishop => 'b',
+			Self::Knight => 'n',
+			Self::Rook => 'r',
+			Self::Pawn => 'p',
+		}
+	}
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceColor {
+	#[default]
+	White,
+	Black,
+}
+
+impl PieceColor {
+	pub const fn not(self) -> Self {
+		match self {
+			Self::Black => Self::White,
+			Self::White => Self::Black,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+	pub piece_type: Pieces,
+	pub color: PieceColor,
+}
+
+impl Piece {
+	pub const fn new(piece_type: Pieces, color: PieceColor) -> Self {
+		Self { piece_type, color }
+	}
+}
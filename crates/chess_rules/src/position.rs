@@ -0,0 +1,60 @@
+use std::{fmt, str::FromStr};
+
+use crate::BOARD_SIZE;
+
+/// A square given by its row (rank) and column (file), both counted from zero at a1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+	pub row: i8,
+	pub col: i8,
+}
+
+impl Position {
+	pub const fn new(row: i8, col: i8) -> Self {
+		Self { row, col }
+	}
+
+	pub const fn from_index(index: i8) -> Self {
+		Self::new(index / BOARD_SIZE, index % BOARD_SIZE)
+	}
+
+	/// Index of the square in a board array, `0` being a1 and `63` being h8
+	pub const fn index(self) -> i8 {
+		self.row * BOARD_SIZE + self.col
+	}
+
+	pub const fn is_on_board(self) -> bool {
+		self.row >= 0 && self.row < BOARD_SIZE && self.col >= 0 && self.col < BOARD_SIZE
+	}
+}
+
+/// Writes the square in algebraic notation, like `e4`
+impl fmt::Display for Position {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let file = char::from(b'a' + self.col as u8);
+		write!(f, "{file}{}", self.row + 1)
+	}
+}
+
+/// Returned when a string isn't a square in algebraic notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsePositionError;
+
+impl fmt::Display for ParsePositionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "expected a square like e4")
+	}
+}
+
+impl std::error::Error for ParsePositionError {}
+
+impl FromStr for Position {
+	type Err = ParsePositionError;
+
+	fn from_str(square: &str) -> Result<Self, Self::Err> {
+		let &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] = square.as_bytes() else {
+			return Err(ParsePositionError);
+		};
+		Ok(Self::new((rank - b'1') as i8, (file - b'a') as i8))
+	}
+}
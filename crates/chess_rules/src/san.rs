@@ -0,0 +1,241 @@
+use std::{error::Error, fmt};
+
+use crate::{Board, Move, MoveFlag, Pieces, Position};
+
+/// A move in Standard Algebraic Notation, like `Nbd7`, `O-O-O` or `e8=Q+`.
+///
+/// It displays the way PGN wants it, the alternate form (`{:#}`) also marks en passant captures
+/// with ` e.p.` as they are often written for people
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct San {
+	notation: String,
+	en_passant: bool,
+}
+
+impl fmt::Display for San {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !f.alternate() || !self.en_passant {
+			return write!(f, "{}", self.notation);
+		}
+
+		// The check suffix goes after the en passant mark
+		let split = self.notation.trim_end_matches(['+', '#']).len();
+		let (mv, check) = self.notation.split_at(split);
+		write!(f, "{mv} e.p.{check}")
+	}
+}
+
+/// Why a SAN string couldn't be turned into a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+	/// The string isn't written like a move at all
+	Invalid(String),
+	/// No legal move in the position matches
+	Illegal(String),
+	/// More than one legal move matches, so the string needs more disambiguation
+	Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Invalid(san) => write!(f, "'{san}' is not a move in algebraic notation"),
+			Self::Illegal(san) => write!(f, "'{san}' is not a legal move in this position"),
+			Self::Ambiguous(san) => write!(f, "'{san}' matches more than one legal move"),
+		}
+	}
+}
+
+impl Error for SanError {}
+
+fn piece_from_letter(letter: char) -> Option<Pieces> {
+	match letter {
+		'K' => Some(Pieces::King),
+		'Q' => Some(Pieces::Queen),
+		'R' => Some(Pieces::Rook),
+		'B' => Some(Pieces::Bishop),
+		'N' => Some(Pieces::Knight),
+		_ => None,
+	}
+}
+
+/// Everything a SAN string says about a move other than castling
+#[derive(Debug, Default)]
+struct SanParts {
+	piece_type: Option<Pieces>,
+	from_col: Option<i8>,
+	from_row: Option<i8>,
+	to: i8,
+	promotion: Option<Pieces>,
+	/// Written with `x` or `:`
+	capture: bool,
+}
+
+impl SanParts {
+	fn parse(san: &str) -> Option<Self> {
+		let capture = san.contains(['x', ':']);
+		let mut chars: Vec<char> = san.chars().filter(|&x| x != 'x' && x != ':').collect();
+
+		let piece_type = chars.first().copied().and_then(piece_from_letter);
+		if piece_type.is_some() {
+			chars.remove(0);
+		}
+
+		// Promotions are written `e8=Q`, but `e8Q` is common enough to accept as well
+		let mut promotion = None;
+		if let Some(&last) = chars.last() {
+			if let Some(piece_type) = piece_from_letter(last.to_ascii_uppercase()) {
+				if last.is_ascii_uppercase() || chars.ends_with(&['=', last]) {
+					promotion = Some(piece_type);
+					chars.pop();
+					if chars.last() == Some(&'=') {
+						chars.pop();
+					}
+				}
+			}
+		}
+
+		if chars.len() < 2 || chars.len() > 4 {
+			return None;
+		}
+		let (disambiguation, target) = chars.split_at(chars.len() - 2);
+		let to = target.iter().collect::<String>().parse::<Position>().ok()?;
+
+		let mut parts = Self {
+			piece_type,
+			to: to.index(),
+			promotion,
+			capture,
+			..Self::default()
+		};
+		for &symbol in disambiguation {
+			match symbol {
+				'a'..='h' if parts.from_col.is_none() => {
+					parts.from_col = Some(symbol as i8 - 'a' as i8);
+				}
+				'1'..='8' if parts.from_row.is_none() => {
+					parts.from_row = Some(symbol as i8 - '1' as i8);
+				}
+				_ => return None,
+			}
+		}
+		Some(parts)
+	}
+
+	fn matches(&self, mv: Move) -> bool {
+		let from = Position::from_index(mv.from);
+		// A pawn written without its file, like `e4`, moves straight ahead
+		let from_col = match self.piece_type {
+			None => self.from_col.or(Some(Position::from_index(self.to).col)),
+			Some(_) => self.from_col,
+		};
+		mv.flag != MoveFlag::Castle
+			&& mv.piece.piece_type == self.piece_type.unwrap_or(Pieces::Pawn)
+			&& mv.to == self.to
+			&& mv.promotion == self.promotion
+			&& mv.is_capture() == self.capture
+			&& from_col.map_or(true, |col| col == from.col)
+			&& self.from_row.map_or(true, |row| row == from.row)
+	}
+}
+
+impl Board {
+	/// Writes `mv` in Standard Algebraic Notation. The move has to be legal in this position,
+	/// otherwise its check suffix and disambiguation make no sense
+	pub fn san(&self, mv: Move) -> San {
+		let mut notation = String::new();
+		let to = Position::from_index(mv.to);
+		let from = Position::from_index(mv.from);
+
+		if let Some((rook_start, _)) = mv.castling_rook_squares() {
+			notation.push_str(if rook_start > mv.from { "O-O" } else { "O-O-O" });
+		} else if mv.piece.piece_type == Pieces::Pawn {
+			if mv.is_capture() {
+				notation.push(char::from(b'a' + from.col as u8));
+				notation.push('x');
+			}
+			notation.push_str(&to.to_string());
+			if let Some(piece_type) = mv.promotion {
+				notation.push('=');
+				notation.push(piece_type.symbol().to_ascii_uppercase());
+			}
+		} else {
+			notation.push(mv.piece.piece_type.symbol().to_ascii_uppercase());
+			notation.push_str(&self.disambiguation(mv));
+			if mv.is_capture() {
+				notation.push('x');
+			}
+			notation.push_str(&to.to_string());
+		}
+
+		let mut board = self.clone();
+		board.make_move(mv);
+		if board.is_in_check(board.side_to_move) {
+			notation.push(if board.has_legal_moves() { '+' } else { '#' });
+		}
+
+		San {
+			notation,
+			en_passant: mv.flag == MoveFlag::EnPassant,
+		}
+	}
+
+	/// The file, rank or square that tells `mv` apart from the moves of other pieces of the same
+	/// kind that can go to the same square. The file is preferred, then the rank
+	fn disambiguation(&self, mv: Move) -> String {
+		let from = Position::from_index(mv.from);
+		let others: Vec<Position> = self
+			.legal_moves()
+			.into_iter()
+			.filter(|x| x.piece == mv.piece && x.to == mv.to && x.from != mv.from)
+			.map(|x| Position::from_index(x.from))
+			.collect();
+
+		let square = from.to_string();
+		if others.is_empty() {
+			String::new()
+		} else if others.iter().all(|x| x.col != from.col) {
+			square[..1].to_string()
+		} else if others.iter().all(|x| x.row != from.row) {
+			square[1..].to_string()
+		} else {
+			square
+		}
+	}
+
+	/// Finds the legal move `san` describes. Check and annotation suffixes like `+`, `#`, `!?` and
+	/// ` e.p.` are ignored, and castling may also be written with zeros
+	pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+		let trimmed = san
+			.trim()
+			.trim_end_matches(['+', '#', '!', '?'])
+			.trim_end_matches("e.p.")
+			.trim_end();
+
+		let castling = match trimmed {
+			"O-O" | "0-0" => Some(true),
+			"O-O-O" | "0-0-0" => Some(false),
+			_ => None,
+		};
+
+		let candidates: Vec<Move> = if let Some(kingside) = castling {
+			self.legal_moves()
+				.into_iter()
+				.filter(|x| x.flag == MoveFlag::Castle && (x.to > x.from) == kingside)
+				.collect()
+		} else {
+			let parts =
+				SanParts::parse(trimmed).ok_or_else(|| SanError::Invalid(san.to_string()))?;
+			self.legal_moves()
+				.into_iter()
+				.filter(|&x| parts.matches(x))
+				.collect()
+		};
+
+		match candidates.as_slice() {
+			[mv] => Ok(*mv),
+			[] => Err(SanError::Illegal(san.to_string())),
+			_ => Err(SanError::Ambiguous(san.to_string())),
+		}
+	}
+}
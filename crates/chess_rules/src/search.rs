@@ -0,0 +1,617 @@
+use std::{
+	fmt,
+	sync::atomic::{AtomicBool, Ordering},
+	time::{Duration, Instant},
+};
+
+use crate::{
+	eval::piece_value,
+	skill::{Rng, SkillLevel},
+	Board, EvalParams, Move, MoveList, MAX_MOVES,
+};
+
+/// Score of delivering mate right away. Mates further away score one less for each ply
+const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+/// Deepest line the search follows, including extensions and quiescence
+const MAX_PLY: usize = 128;
+/// How often the clock and the stop flag are looked at, in nodes
+const CHECK_INTERVAL: u64 = 2048;
+
+/// When a search has to stop. Without any limit it only stops once told to
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+	pub depth: Option<u8>,
+	pub nodes: Option<u64>,
+	pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+	pub const fn depth(depth: u8) -> Self {
+		Self {
+			depth: Some(depth),
+			nodes: None,
+			time: None,
+		}
+	}
+
+	pub const fn move_time(time: Duration) -> Self {
+		Self {
+			depth: None,
+			nodes: None,
+			time: Some(time),
+		}
+	}
+
+	/// Thinking time for one move when `remaining` is left on the clock, `increment` comes back
+	/// after the move and `moves_to_go` moves have to be played before the next time control, if
+	/// there is one
+	pub fn for_clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Self {
+		// Sudden death is planned as if the game went on for another 30 moves
+		let moves_to_go = moves_to_go.unwrap_or(30).max(1);
+		let share = remaining / moves_to_go + increment * 3 / 4;
+		// Some time is left over for the moves that follow and for getting the move on the board
+		let safety_margin = Duration::from_millis(50);
+		let time = share.min(remaining / 2).max(Duration::from_millis(10));
+		Self::move_time(time.min(remaining.saturating_sub(safety_margin)))
+	}
+}
+
+/// How good a position is for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+	Centipawns(i32),
+	/// Mate in that many moves, negative when the side to move gets mated
+	Mate(i32),
+}
+
+impl Score {
+	fn from_search(score: i32) -> Self {
+		if score.abs() < MATE - MAX_PLY as i32 {
+			return Self::Centipawns(score);
+		}
+
+		let moves = (MATE - score.abs() + 1) / 2;
+		Self::Mate(if score > 0 { moves } else { -moves })
+	}
+}
+
+impl fmt::Display for Score {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f32 / 100.0),
+			Self::Mate(moves) => write!(f, "#{moves}"),
+		}
+	}
+}
+
+/// What the search found once it finished a depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+	pub depth: u8,
+	pub score: Score,
+	pub nodes: u64,
+	pub time: Duration,
+	/// Permille of the transposition table in use
+	pub hashfull: u32,
+	/// Rank of this line among the best ones, counted from 1. Only the best line is reported
+	/// unless [`Searcher::set_multi_pv`] asks for more
+	pub multi_pv: usize,
+	/// The line both sides are expected to play, starting with the move it's about
+	pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+	Exact,
+	/// The score is at least this high, the search stopped at a move that was good enough
+	Lower,
+	/// The score is at most this high, no move reached the window
+	Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TableEntry {
+	key: u64,
+	depth: u8,
+	score: i32,
+	bound: Bound,
+	best_move: Option<Move>,
+}
+
+/// Iterative deepening alpha-beta search with quiescence search and a transposition table.
+///
+/// The table, killer moves and history scores are kept between searches, so the searcher of one
+/// game should be reused for all of its moves
+pub struct Searcher {
+	table: Vec<Option<TableEntry>>,
+	/// Two quiet moves per ply that caused a cutoff in a sibling position
+	killers: [[Option<Move>; 2]; MAX_PLY],
+	/// How often quiet moves caused cutoffs, by piece and target square
+	history: [[i32; 64]; 12],
+	/// Keys of the earlier positions of the game followed by the line being searched
+	keys: Vec<u64>,
+	limits: SearchLimits,
+	start: Instant,
+	nodes: u64,
+	root_depth: u8,
+	root_best: Option<Move>,
+	/// Scores of every root move in the depth being searched, for a weakened choice
+	root_scores: Vec<(Move, i32)>,
+	stopped: bool,
+	skill: SkillLevel,
+	rng: Rng,
+	/// This move is chosen without quiescence search, so captures that can be answered look good
+	blind: bool,
+	params: EvalParams,
+	/// Number of best lines reported
+	multi_pv: usize,
+}
+
+impl Default for Searcher {
+	fn default() -> Self {
+		Self::new(16)
+	}
+}
+
+impl Searcher {
+	/// A searcher with a transposition table of roughly `hash_megabytes`
+	pub fn new(hash_megabytes: usize) -> Self {
+		let mut searcher = Self {
+			table: Vec::new(),
+			killers: [[None; 2]; MAX_PLY],
+			history: [[0; 64]; 12],
+			keys: Vec::new(),
+			limits: SearchLimits::default(),
+			start: Instant::now(),
+			nodes: 0,
+			root_depth: 0,
+			root_best: None,
+			root_scores: Vec::new(),
+			stopped: false,
+			skill: SkillLevel::default(),
+			rng: Rng::from_time(),
+			blind: false,
+			params: EvalParams::default(),
+			multi_pv: 1,
+		};
+		searcher.resize_table(hash_megabytes);
+		searcher
+	}
+
+	/// Replaces the transposition table by an empty one of roughly `hash_megabytes`
+	pub fn resize_table(&mut self, hash_megabytes: usize) {
+		let entries =
+			(hash_megabytes * 1024 * 1024 / std::mem::size_of::<Option<TableEntry>>()).max(1);
+		// A power of two lets a key be turned into an index by masking
+		self.table = vec![None; 1 << entries.ilog2()];
+	}
+
+	/// Permille of the transposition table in use, judged by its first thousand entries
+	pub fn hashfull(&self) -> u32 {
+		let sample = &self.table[..self.table.len().min(1000)];
+		(sample.iter().filter(|x| x.is_some()).count() * 1000 / sample.len()) as u32
+	}
+
+	/// Forgets everything learned about earlier positions, for a new game
+	pub fn clear(&mut self) {
+		self.table.fill(None);
+		self.killers = [[None; 2]; MAX_PLY];
+		self.history = [[0; 64]; 12];
+	}
+
+	pub fn set_skill_level(&mut self, skill: SkillLevel) {
+		self.skill = skill;
+	}
+
+	/// Reports the `lines` best moves with their lines instead of only the best one. Scoring more
+	/// than one move exactly makes the search slower
+	pub fn set_multi_pv(&mut self, lines: usize) {
+		self.multi_pv = lines.max(1);
+	}
+
+	/// Evaluates positions with `params` instead of the built-in weights
+	pub fn set_eval_params(&mut self, params: EvalParams) {
+		self.params = params;
+	}
+
+	/// Makes the choices of a weakened engine repeatable
+	pub fn seed(&mut self, seed: u64) {
+		self.rng = Rng::new(seed);
+	}
+
+	/// Looks for the best move on `board` until `limits` are reached or `stop` is set, reporting
+	/// every finished depth to `report`. `game_keys` holds the Zobrist keys of the positions played
+	/// before `board`, so that repeating them is seen as a draw.
+	///
+	/// The first depth is always finished, so a legal move is found whenever there is one. Below
+	/// the maximum skill level the limits are tightened and the move played isn't always the best
+	pub fn search(
+		&mut self,
+		board: &Board,
+		game_keys: &[u64],
+		limits: SearchLimits,
+		stop: &AtomicBool,
+		mut report: impl FnMut(&SearchInfo),
+	) -> Option<Move> {
+		let mut board = board.clone();
+		let mut best_move = board.legal_moves().first().copied()?;
+
+		self.blind = self.rng.percent_chance(self.skill.blunder_percent());
+		let mut limits = self.skill.limit(limits);
+		if self.blind {
+			limits.depth = Some(1);
+		}
+
+		self.keys = game_keys.to_vec();
+		self.limits = limits;
+		self.start = Instant::now();
+		self.nodes = 0;
+		self.stopped = false;
+		// Old cutoffs say less about the new position
+		for entry in self.history.iter_mut().flatten() {
+			*entry /= 8;
+		}
+
+		let mut root_scores = Vec::new();
+		let max_depth = limits.depth.unwrap_or(u8::MAX).clamp(1, MAX_PLY as u8 - 1);
+		for depth in 1..=max_depth {
+			if depth > 1 && stop.load(Ordering::Relaxed) {
+				break;
+			}
+			self.root_depth = depth;
+			self.root_best = None;
+			self.root_scores.clear();
+			let score = self.negamax(&mut board, depth as i32, 0, -INFINITY, INFINITY, stop);
+			if self.stopped {
+				break;
+			}
+
+			if let Some(mv) = self.root_best {
+				best_move = mv;
+			}
+			root_scores.clone_from(&self.root_scores);
+
+			let mut lines = vec![(best_move, score)];
+			if self.multi_pv > 1 {
+				lines.clone_from(&root_scores);
+				lines.sort_by_key(|x| std::cmp::Reverse(x.1));
+				lines.truncate(self.multi_pv);
+			}
+			for (index, &(mv, line_score)) in lines.iter().enumerate() {
+				report(&SearchInfo {
+					depth,
+					score: Score::from_search(line_score),
+					nodes: self.nodes,
+					time: self.start.elapsed(),
+					hashfull: self.hashfull(),
+					multi_pv: index + 1,
+					pv: self.principal_variation(&board, mv, depth),
+				});
+			}
+
+			// Another depth takes longer than all the ones before it, don't start what can't end
+			let half_time_used = limits
+				.time
+				.is_some_and(|time| self.start.elapsed() * 2 >= time);
+			let mate_found = matches!(
+				Score::from_search(score),
+				Score::Mate(moves) if moves.abs() * 2 <= depth as i32
+			);
+			if half_time_used || mate_found {
+				break;
+			}
+		}
+
+		// The reports keep showing the best move, only the move played is weakened
+		if self.skill.temperature() > 0 {
+			best_move = self
+				.skill
+				.choose(&root_scores, &mut self.rng)
+				.unwrap_or(best_move);
+		}
+
+		Some(best_move)
+	}
+
+	fn negamax(
+		&mut self,
+		board: &mut Board,
+		depth: i32,
+		ply: usize,
+		mut alpha: i32,
+		beta: i32,
+		stop: &AtomicBool,
+	) -> i32 {
+		if ply > 0 && (board.halfmove_clock >= 100 || self.is_repetition(board)) {
+			return 0;
+		}
+		if self.count_node(stop) {
+			return 0;
+		}
+
+		let in_check = board.is_in_check(board.side_to_move);
+		// Checks are looked at one ply deeper, so forced sequences don't vanish over the horizon
+		let depth = if in_check { depth + 1 } else { depth };
+		if depth <= 0 || ply >= MAX_PLY - 1 {
+			return self.quiescence(board, ply, alpha, beta, stop);
+		}
+
+		let key = board.zobrist_key();
+		let table_move = match self.probe(key) {
+			Some(entry) => {
+				let score = score_from_table(entry.score, ply);
+				let usable = ply > 0
+					&& entry.depth as i32 >= depth
+					&& match entry.bound {
+						Bound::Exact => true,
+						Bound::Lower => score >= beta,
+						Bound::Upper => score <= alpha,
+					};
+				if usable {
+					return score;
+				}
+				entry.best_move
+			}
+			None => None,
+		};
+
+		let moves = board.legal_moves();
+		if moves.is_empty() {
+			return if in_check { -MATE + ply as i32 } else { 0 };
+		}
+		let mut scores = self.order_moves(&moves, table_move, ply);
+
+		let original_alpha = alpha;
+		let mut best_score = -INFINITY;
+		let mut best_move = None;
+		self.keys.push(key);
+
+		for index in 0..moves.len() {
+			let mv = moves[pick_next(&mut scores, moves.len())];
+			let undo_info = board.make_move(mv);
+			// Every move after the first is only checked to be worse than the best one so far,
+			// which is cheaper than finding its exact score
+			let mut score;
+			if ply == 0 && (self.skill.temperature() > 0 || self.multi_pv > 1) {
+				// A weakened choice and reporting several lines need the exact score of every
+				// move, not just of the best one
+				score = -self.negamax(board, depth - 1, ply + 1, -INFINITY, INFINITY, stop);
+			} else if index == 0 {
+				score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, stop);
+			} else {
+				score = -self.negamax(board, depth - 1, ply + 1, -alpha - 1, -alpha, stop);
+				if score > alpha && score < beta {
+					score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, stop);
+				}
+			}
+			board.unmake_move(mv, undo_info);
+
+			if self.stopped {
+				self.keys.pop();
+				return 0;
+			}
+
+			if ply == 0 {
+				self.root_scores.push((mv, score));
+			}
+			if score > best_score {
+				best_score = score;
+				best_move = Some(mv);
+				if ply == 0 {
+					self.root_best = Some(mv);
+				}
+			}
+			alpha = alpha.max(score);
+			if alpha >= beta {
+				if !mv.is_capture() && mv.promotion.is_none() {
+					self.remember_quiet_cutoff(mv, depth, ply);
+				}
+				break;
+			}
+		}
+		self.keys.pop();
+
+		let bound = if best_score <= original_alpha {
+			Bound::Upper
+		} else if best_score >= beta {
+			Bound::Lower
+		} else {
+			Bound::Exact
+		};
+		self.store(TableEntry {
+			key,
+			depth: depth as u8,
+			score: score_to_table(best_score, ply),
+			bound,
+			best_move,
+		});
+
+		best_score
+	}
+
+	/// Plays out captures and promotions until the position is quiet, so that a position isn't
+	/// judged in the middle of an exchange
+	fn quiescence(
+		&mut self,
+		board: &mut Board,
+		ply: usize,
+		mut alpha: i32,
+		beta: i32,
+		stop: &AtomicBool,
+	) -> i32 {
+		if self.count_node(stop) {
+			return 0;
+		}
+
+		// Not capturing anything is an option too, so the static evaluation is a lower bound
+		let stand_pat = board.evaluate_with(&self.params);
+		if stand_pat >= beta || ply >= MAX_PLY - 1 || self.blind {
+			return stand_pat;
+		}
+		alpha = alpha.max(stand_pat);
+
+		let mut moves = board.legal_moves();
+		moves.retain(|mv| mv.is_capture() || mv.promotion.is_some());
+		let mut scores = self.order_moves(&moves, None, ply);
+
+		for _ in 0..moves.len() {
+			let mv = moves[pick_next(&mut scores, moves.len())];
+			let undo_info = board.make_move(mv);
+			let score = -self.quiescence(board, ply + 1, -beta, -alpha, stop);
+			board.unmake_move(mv, undo_info);
+
+			if self.stopped {
+				return 0;
+			}
+			if score >= beta {
+				return score;
+			}
+			alpha = alpha.max(score);
+		}
+
+		alpha
+	}
+
+	/// Counts a visited node and tells whether the search has to stop. The first depth is never
+	/// stopped early
+	fn count_node(&mut self, stop: &AtomicBool) -> bool {
+		self.nodes += 1;
+		if self.nodes % CHECK_INTERVAL == 0 && self.root_depth > 1 {
+			self.stopped = stop.load(Ordering::Relaxed)
+				|| self
+					.limits
+					.time
+					.is_some_and(|time| self.start.elapsed() >= time)
+				|| self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+		}
+		self.stopped
+	}
+
+	/// Whether the position already came up since the last capture or pawn move. Repeating once is
+	/// scored as a draw already, since whoever repeated could do so again
+	fn is_repetition(&self, board: &Board) -> bool {
+		let key = board.zobrist_key();
+		self.keys
+			.iter()
+			.rev()
+			.take(board.halfmove_clock as usize)
+			.skip(1)
+			.step_by(2)
+			.any(|&x| x == key)
+	}
+
+	/// Sort keys for `moves`: the table move first, then captures of the most valuable piece by the
+	/// least valuable one, then killer moves and then the quiet moves that caused the most cutoffs
+	fn order_moves(
+		&self,
+		moves: &MoveList,
+		table_move: Option<Move>,
+		ply: usize,
+	) -> [i32; MAX_MOVES] {
+		let mut scores = [0; MAX_MOVES];
+		for (score, &mv) in scores.iter_mut().zip(moves.iter()) {
+			*score = if Some(mv) == table_move {
+				2_000_000
+			} else if mv.is_capture() || mv.promotion.is_some() {
+				let victim = mv.captured.map_or(0, |x| piece_value(x.piece_type));
+				let promotion = mv.promotion.map_or(0, piece_value);
+				1_000_000 + (victim + promotion) * 10 - piece_value(mv.piece.piece_type) / 10
+			} else if self.killers[ply][0] == Some(mv) {
+				900_000
+			} else if self.killers[ply][1] == Some(mv) {
+				800_000
+			} else {
+				self.history[history_index(mv)][mv.to as usize]
+			};
+		}
+		scores
+	}
+
+	fn remember_quiet_cutoff(&mut self, mv: Move, depth: i32, ply: usize) {
+		let killers = &mut self.killers[ply];
+		if killers[0] != Some(mv) {
+			killers[1] = killers[0];
+			killers[0] = Some(mv);
+		}
+
+		// Kept well below the killer moves' sort keys
+		let entry = &mut self.history[history_index(mv)][mv.to as usize];
+		*entry = (*entry + depth * depth).min(500_000);
+	}
+
+	fn probe(&self, key: u64) -> Option<TableEntry> {
+		self.table[key as usize & (self.table.len() - 1)].filter(|x| x.key == key)
+	}
+
+	fn store(&mut self, entry: TableEntry) {
+		let index = entry.key as usize & (self.table.len() - 1);
+		// Deeper results are worth more, unless they belong to another position
+		let replace = match self.table[index] {
+			Some(old) => {
+				old.key != entry.key || entry.depth >= old.depth || entry.bound == Bound::Exact
+			}
+			None => true,
+		};
+		if replace {
+			self.table[index] = Some(entry);
+		}
+	}
+
+	/// Follows the best moves stored in the table from the root
+	fn principal_variation(&self, board: &Board, best_move: Move, depth: u8) -> Vec<Move> {
+		let mut board = board.clone();
+		let mut pv = vec![best_move];
+		board.make_move(best_move);
+
+		while pv.len() < depth as usize {
+			let Some(mv) = self.probe(board.zobrist_key()).and_then(|x| x.best_move) else {
+				break;
+			};
+			if !board.legal_moves().contains(&mv) {
+				break;
+			}
+			pv.push(mv);
+			board.make_move(mv);
+		}
+
+		pv
+	}
+}
+
+/// Index of the highest sort key among the first `len`, which is then used up
+fn pick_next(scores: &mut [i32; MAX_MOVES], len: usize) -> usize {
+	let mut best = 0;
+	for index in 1..len {
+		if scores[index] > scores[best] {
+			best = index;
+		}
+	}
+	scores[best] = i32::MIN;
+	best
+}
+
+fn history_index(mv: Move) -> usize {
+	mv.piece.color as usize * 6 + mv.piece.piece_type as usize
+}
+
+// Mate scores count from the root, the table stores them counted from the position itself
+fn score_to_table(score: i32, ply: usize) -> i32 {
+	if score >= MATE - MAX_PLY as i32 {
+		score + ply as i32
+	} else if score <= -MATE + MAX_PLY as i32 {
+		score - ply as i32
+	} else {
+		score
+	}
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+	if score >= MATE - MAX_PLY as i32 {
+		score - ply as i32
+	} else if score <= -MATE + MAX_PLY as i32 {
+		score + ply as i32
+	} else {
+		score
+	}
+}
@@ -0,0 +1,158 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use strum::{Display, EnumIter};
+
+use crate::{Move, SearchLimits};
+
+/// How well the engine plays. Below [`SkillLevel::Maximum`] it searches less, picks among the
+/// moves close to the best one instead of always the best, and now and then plays without looking
+/// past the first move, missing whatever tactics the position holds
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter)]
+pub enum SkillLevel {
+	Beginner,
+	Novice,
+	Intermediate,
+	Advanced,
+	Expert,
+	#[default]
+	Maximum,
+}
+
+impl SkillLevel {
+	/// Nodes searched per move at most
+	pub const fn node_limit(self) -> Option<u64> {
+		match self {
+			Self::Beginner => Some(5_000),
+			Self::Novice => Some(20_000),
+			Self::Intermediate => Some(80_000),
+			Self::Advanced => Some(300_000),
+			Self::Expert => Some(1_500_000),
+			Self::Maximum => None,
+		}
+	}
+
+	pub const fn depth_limit(self) -> Option<u8> {
+		match self {
+			Self::Beginner => Some(2),
+			Self::Novice => Some(3),
+			Self::Intermediate => Some(4),
+			Self::Advanced => Some(6),
+			Self::Expert | Self::Maximum => None,
+		}
+	}
+
+	/// Part of the thinking time the clock allows that is used, in percent
+	pub const fn time_percent(self) -> u32 {
+		match self {
+			Self::Beginner => 30,
+			Self::Novice => 40,
+			Self::Intermediate => 50,
+			Self::Advanced => 70,
+			Self::Expert | Self::Maximum => 100,
+		}
+	}
+
+	/// Centipawns a move can be worse than the best one and still be chosen fairly often. Moves
+	/// get less likely the worse they are, at zero the best move is always played
+	pub const fn temperature(self) -> i32 {
+		match self {
+			Self::Beginner => 150,
+			Self::Novice => 90,
+			Self::Intermediate => 50,
+			Self::Advanced => 25,
+			Self::Expert => 10,
+			Self::Maximum => 0,
+		}
+	}
+
+	/// Chance of a move being chosen by only looking one ply ahead, in percent
+	pub const fn blunder_percent(self) -> u32 {
+		match self {
+			Self::Beginner => 20,
+			Self::Novice => 12,
+			Self::Intermediate => 6,
+			Self::Advanced => 3,
+			Self::Expert => 1,
+			Self::Maximum => 0,
+		}
+	}
+
+	/// `limits` tightened to what this level searches
+	pub fn limit(self, limits: SearchLimits) -> SearchLimits {
+		SearchLimits {
+			depth: tighter(limits.depth, self.depth_limit()),
+			nodes: tighter(limits.nodes, self.node_limit()),
+			time: limits.time.map(|x| x * self.time_percent() / 100),
+		}
+	}
+
+	/// Picks one of the scored root moves, the better ones being more likely
+	pub(crate) fn choose(self, root_scores: &[(Move, i32)], rng: &mut Rng) -> Option<Move> {
+		let best = root_scores.iter().map(|x| x.1).max()?;
+		let temperature = self.temperature();
+		if temperature == 0 {
+			return root_scores.iter().find(|x| x.1 == best).map(|x| x.0);
+		}
+
+		// Moves far behind the best one don't count at all, which also keeps mates out of reach
+		// from being thrown away for a quiet move
+		let weights: Vec<_> = root_scores
+			.iter()
+			.map(|&(mv, score)| {
+				let behind = f64::from(best - score) / f64::from(temperature);
+				(mv, if behind > 4.0 { 0.0 } else { (-behind).exp() })
+			})
+			.collect();
+
+		let mut pick = rng.next_f64() * weights.iter().map(|x| x.1).sum::<f64>();
+		for &(mv, weight) in &weights {
+			if pick < weight {
+				return Some(mv);
+			}
+			pick -= weight;
+		}
+		weights.iter().rev().find(|x| x.1 > 0.0).map(|x| x.0)
+	}
+}
+
+/// The lower of two optional limits, where `None` means no limit
+fn tighter<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(a.min(b)),
+		(a, b) => a.or(b),
+	}
+}
+
+/// Small xorshift generator for the engine's choices, which don't need to be unpredictable
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		// The state must never be zero
+		Self(seed | 1)
+	}
+
+	pub fn from_time() -> Self {
+		let nanos = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |x| x.as_nanos() as u64);
+		Self::new(nanos)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	/// Uniformly distributed in `[0, 1)`
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	pub fn percent_chance(&mut self, percent: u32) -> bool {
+		self.next_u64() % 100 < u64::from(percent)
+	}
+}
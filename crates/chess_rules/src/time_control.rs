@@ -0,0 +1,248 @@
+use std::{error::Error, fmt, str::FromStr, time::Duration};
+
+/// Time a player gets back for every move they make
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeBonus {
+	#[default]
+	None,
+	/// Fischer increment, added after every move no matter how long it took
+	Increment(Duration),
+	/// Bronstein delay, the time used for the move is given back up to this much
+	Bronstein(Duration),
+	/// US simple delay, the clock only starts running once this much of the turn has passed
+	SimpleDelay(Duration),
+}
+
+/// A period of the game with its own time, like the first 40 moves in 90 minutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeControlStage {
+	/// Moves to make in this stage, `None` for sudden death until the end of the game
+	pub moves: Option<u32>,
+	/// Time added to the clock when the stage begins
+	pub time: Duration,
+	pub bonus: TimeBonus,
+}
+
+/// How much time each side has for the game, one stage after the other.
+///
+/// If the last stage has a move count, it starts over every time it's completed. Without any
+/// stages there is no time limit at all. Written in the syntax of the PGN `TimeControl` tag, like
+/// `40/5400:1800+30`. PGN has no way to write delays, so they are written like an increment with a
+/// `d` after it for simple delay or a `b` for Bronstein delay, like `300+5d`. Those can't go in a
+/// PGN file, [`TimeControl::to_pgn`] leaves them out
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+	pub stages: Vec<TimeControlStage>,
+}
+
+impl TimeControl {
+	pub const fn unlimited() -> Self {
+		Self { stages: Vec::new() }
+	}
+
+	/// The whole game in `time`, with `bonus` after every move
+	pub fn sudden_death(time: Duration, bonus: TimeBonus) -> Self {
+		Self {
+			stages: vec![TimeControlStage {
+				moves: None,
+				time,
+				bonus,
+			}],
+		}
+	}
+
+	pub fn is_unlimited(&self) -> bool {
+		self.stages.is_empty()
+	}
+
+	/// Time on the clock at the start of the game, `None` if there is no limit
+	pub fn initial_time(&self) -> Option<Duration> {
+		self.stages.first().map(|x| x.time)
+	}
+
+	/// The stage a player who already made `moves_made` moves is in, and how many of its moves
+	/// they have made
+	fn locate(&self, moves_made: u32) -> Option<(usize, u32)> {
+		let mut remaining = moves_made;
+		for (index, stage) in self.stages.iter().enumerate() {
+			match stage.moves {
+				Some(moves) if remaining >= moves => remaining -= moves,
+				_ => return Some((index, remaining)),
+			}
+		}
+
+		// Every stage is done, so the last one starts over
+		let last = self.stages.len().checked_sub(1)?;
+		let moves = self.stages[last].moves.unwrap_or(u32::MAX);
+		Some((last, remaining % moves))
+	}
+
+	/// Whether any stage has a simple or Bronstein delay, which the PGN `TimeControl` tag can't hold
+	pub fn has_delay(&self) -> bool {
+		self.stages
+			.iter()
+			.any(|x| matches!(x.bonus, TimeBonus::Bronstein(_) | TimeBonus::SimpleDelay(_)))
+	}
+
+	/// The time control as the PGN `TimeControl` tag is written, without any delays
+	pub fn to_pgn(&self) -> String {
+		let mut without_delays = self.clone();
+		for stage in &mut without_delays.stages {
+			if matches!(
+				stage.bonus,
+				TimeBonus::Bronstein(_) | TimeBonus::SimpleDelay(_)
+			) {
+				stage.bonus = TimeBonus::None;
+			}
+		}
+		without_delays.to_string()
+	}
+
+	/// Moves a player who already made `moves_made` moves still has to make before the time of the
+	/// next stage is added, `None` when their stage lasts the rest of the game
+	pub fn moves_to_go(&self, moves_made: u32) -> Option<u32> {
+		let (index, stage_moves) = self.locate(moves_made)?;
+		self.stages[index].moves.map(|x| x - stage_moves)
+	}
+
+	/// The stage the next move of a player who already made `moves_made` moves is played in
+	pub fn stage(&self, moves_made: u32) -> Option<&TimeControlStage> {
+		self.locate(moves_made)
+			.map(|(index, _)| &self.stages[index])
+	}
+
+	/// How long the clock waits before running on the next move of a player who already made
+	/// `moves_made` moves
+	pub fn simple_delay(&self, moves_made: u32) -> Duration {
+		match self.stage(moves_made).map(|x| x.bonus) {
+			Some(TimeBonus::SimpleDelay(delay)) => delay,
+			_ => Duration::ZERO,
+		}
+	}
+
+	/// Time added to the clock of a player who already made `moves_made` moves when they make the
+	/// next one after thinking for `time_used`: the bonus of the stage, and the time of the next
+	/// stage if the move completes this one
+	pub fn time_after_move(&self, moves_made: u32, time_used: Duration) -> Duration {
+		let Some((index, stage_moves)) = self.locate(moves_made) else {
+			return Duration::ZERO;
+		};
+		let stage = &self.stages[index];
+
+		let mut added = match stage.bonus {
+			TimeBonus::Increment(increment) => increment,
+			TimeBonus::Bronstein(delay) => delay.min(time_used),
+			TimeBonus::None | TimeBonus::SimpleDelay(_) => Duration::ZERO,
+		};
+		if stage.moves == Some(stage_moves + 1) {
+			added += self
+				.stage(moves_made + 1)
+				.map_or(Duration::ZERO, |x| x.time);
+		}
+		added
+	}
+}
+
+/// Five minutes for the whole game
+impl Default for TimeControl {
+	fn default() -> Self {
+		Self::sudden_death(Duration::from_secs(5 * 60), TimeBonus::None)
+	}
+}
+
+impl fmt::Display for TimeControl {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.is_unlimited() {
+			return write!(f, "-");
+		}
+
+		for (index, stage) in self.stages.iter().enumerate() {
+			if index > 0 {
+				write!(f, ":")?;
+			}
+			if let Some(moves) = stage.moves {
+				write!(f, "{moves}/")?;
+			}
+			write!(f, "{}", stage.time.as_secs())?;
+			match stage.bonus {
+				TimeBonus::None => {}
+				TimeBonus::Increment(increment) => write!(f, "+{}", increment.as_secs())?,
+				TimeBonus::Bronstein(delay) => write!(f, "+{}b", delay.as_secs())?,
+				TimeBonus::SimpleDelay(delay) => write!(f, "+{}d", delay.as_secs())?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Returned when a string isn't a time control in the syntax of the PGN `TimeControl` tag. The
+/// unknown time control `?` and sandclocks aren't understood either
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimeControlError(pub String);
+
+impl fmt::Display for ParseTimeControlError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "'{}' is not a time control like 40/5400:1800+30", self.0)
+	}
+}
+
+impl Error for ParseTimeControlError {}
+
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+	if !seconds.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+	seconds.parse().ok().map(Duration::from_secs)
+}
+
+fn parse_stage(field: &str) -> Option<TimeControlStage> {
+	let (moves, rest) = match field.split_once('/') {
+		Some((moves, rest)) => (Some(moves.parse().ok().filter(|&x| x > 0)?), rest),
+		None => (None, field),
+	};
+
+	let (time, bonus) = match rest.split_once('+') {
+		Some((time, bonus)) => {
+			let bonus = if let Some(delay) = bonus.strip_suffix('d') {
+				TimeBonus::SimpleDelay(parse_seconds(delay)?)
+			} else if let Some(delay) = bonus.strip_suffix('b') {
+				TimeBonus::Bronstein(parse_seconds(delay)?)
+			} else {
+				TimeBonus::Increment(parse_seconds(bonus)?)
+			};
+			(time, bonus)
+		}
+		None => (rest, TimeBonus::None),
+	};
+
+	Some(TimeControlStage {
+		moves,
+		time: parse_seconds(time)?,
+		bonus,
+	})
+}
+
+impl FromStr for TimeControl {
+	type Err = ParseTimeControlError;
+
+	fn from_str(time_control: &str) -> Result<Self, Self::Err> {
+		let error = || ParseTimeControlError(time_control.to_string());
+		let trimmed = time_control.trim();
+		if trimmed == "-" {
+			return Ok(Self::unlimited());
+		}
+
+		let stages = trimmed
+			.split(':')
+			.map(parse_stage)
+			.collect::<Option<Vec<_>>>()
+			.ok_or_else(error)?;
+
+		// Sudden death lasts until the end of the game, nothing can come after it
+		let (_, earlier) = stages.split_last().ok_or_else(error)?;
+		if earlier.iter().any(|x| x.moves.is_none()) {
+			return Err(error());
+		}
+		Ok(Self { stages })
+	}
+}
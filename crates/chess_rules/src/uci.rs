@@ -0,0 +1,128 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{Board, Move, MoveFlag, Pieces, Position};
+
+/// How castling moves are written. Standard chess writes the king's two square step (`e1g1`),
+/// Chess960 writes the king taking its own rook (`e1h1`) since the step can be ambiguous there
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CastlingMode {
+	#[default]
+	Standard,
+	Chess960,
+}
+
+/// A move in the long algebraic notation of the UCI protocol, like `e2e4` or `e7e8q`. It only
+/// names squares, [`UciMove::to_move`] finds the move it means in a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UciMove {
+	pub from: Position,
+	pub to: Position,
+	pub promotion: Option<Pieces>,
+}
+
+/// Why a UCI move string couldn't be turned into a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciMoveError {
+	/// The string isn't two squares and an optional promotion letter
+	Invalid(String),
+	/// No legal move in the position goes between the two squares
+	Illegal(UciMove),
+}
+
+impl fmt::Display for UciMoveError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Invalid(uci) => write!(f, "'{uci}' is not a move in UCI notation"),
+			Self::Illegal(uci) => write!(f, "'{uci}' is not a legal move in this position"),
+		}
+	}
+}
+
+impl Error for UciMoveError {}
+
+impl UciMove {
+	/// Builds the move from board array indices, `0` being a1 and `63` being h8
+	pub const fn from_indices(from: i8, to: i8, promotion: Option<Pieces>) -> Self {
+		Self {
+			from: Position::from_index(from),
+			to: Position::from_index(to),
+			promotion,
+		}
+	}
+
+	pub fn from_move(mv: Move, castling_mode: CastlingMode) -> Self {
+		let to = match (castling_mode, mv.castling_rook_squares()) {
+			(CastlingMode::Chess960, Some((rook_start, _))) => rook_start,
+			_ => mv.to,
+		};
+		Self::from_indices(mv.from, to, mv.promotion)
+	}
+
+	/// Finds the legal move this describes in `board`. Castling is understood in both the
+	/// standard and the Chess960 way of writing it
+	pub fn to_move(self, board: &Board) -> Result<Move, UciMoveError> {
+		let (from, to) = (self.from.index(), self.to.index());
+		board
+			.legal_moves_from(from)
+			.into_iter()
+			.find(|mv| {
+				let king_takes_rook = mv.flag == MoveFlag::Castle
+					&& mv
+						.castling_rook_squares()
+						.is_some_and(|(rook, _)| rook == to);
+				(mv.to == to || king_takes_rook) && mv.promotion == self.promotion
+			})
+			.ok_or(UciMoveError::Illegal(self))
+	}
+}
+
+impl fmt::Display for UciMove {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}{}", self.from, self.to)?;
+		if let Some(piece_type) = self.promotion {
+			write!(f, "{}", piece_type.symbol())?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for UciMove {
+	type Err = UciMoveError;
+
+	fn from_str(uci: &str) -> Result<Self, Self::Err> {
+		let invalid = || UciMoveError::Invalid(uci.to_string());
+		if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+			return Err(invalid());
+		}
+
+		let from = uci[0..2].parse().map_err(|_| invalid())?;
+		let to = uci[2..4].parse().map_err(|_| invalid())?;
+		let promotion = match uci.as_bytes().get(4) {
+			None => None,
+			Some(b'q') => Some(Pieces::Queen),
+			Some(b'r') => Some(Pieces::Rook),
+			Some(b'b') => Some(Pieces::Bishop),
+			Some(b'n') => Some(Pieces::Knight),
+			Some(_) => return Err(invalid()),
+		};
+
+		Ok(Self {
+			from,
+			to,
+			promotion,
+		})
+	}
+}
+
+impl Move {
+	pub fn to_uci(self, castling_mode: CastlingMode) -> UciMove {
+		UciMove::from_move(self, castling_mode)
+	}
+}
+
+impl Board {
+	/// Finds the legal move a UCI string like `e2e4`, `e7e8q`, `e1g1` or `e1h1` describes
+	pub fn parse_uci(&self, uci: &str) -> Result<Move, UciMoveError> {
+		uci.parse::<UciMove>()?.to_move(self)
+	}
+}
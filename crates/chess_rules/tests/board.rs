@@ -0,0 +1,34 @@
+use chess_rules::Board;
+
+#[test]
+fn unmake_restores_everything() {
+	let fens = [
+		// Kiwipete, with clocks that aren't at their start
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 7 20",
+		// En passant on c6, and the pawn on b7 promotes with or without taking
+		"r1bq1b1r/pP1kp1pp/8/2pP4/8/8/P1P1PPPP/RNBQKBNR w KQ c6 0 6",
+		// Black takes en passant or promotes, taking the rook on a1 loses white its castling
+		"r3k3/8/8/8/3pP3/8/1p6/R3K3 b Qq e3 0 40",
+	];
+
+	for fen in fens {
+		let mut board = Board::from_fen(fen).unwrap();
+		let before = board.clone();
+		for mv in before.legal_moves() {
+			let undo_info = board.make_move(mv);
+			board.unmake_move(mv, undo_info);
+
+			assert_eq!(
+				board.castling_rights, before.castling_rights,
+				"{mv} in {fen}"
+			);
+			assert_eq!(board.en_passant, before.en_passant, "{mv} in {fen}");
+			assert_eq!(board.halfmove_clock, before.halfmove_clock, "{mv} in {fen}");
+			assert_eq!(
+				board.fullmove_number, before.fullmove_number,
+				"{mv} in {fen}"
+			);
+			assert_eq!(board, before, "{mv} in {fen}");
+		}
+	}
+}
@@ -0,0 +1,114 @@
+use chess_rules::{Board, EvalParams, EvalParamsError, EvalParamsErrorKind, Pieces, Weight};
+
+fn evaluate(fen: &str) -> i32 {
+	Board::from_fen(fen).unwrap().evaluate()
+}
+
+#[test]
+fn mirrored_positions_evaluate_the_same() {
+	assert_eq!(evaluate(Board::STARTING_FEN), 0);
+	// The same position with the colours swapped and the board turned around
+	let pairs = [
+		(
+			"r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+			"rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4",
+		),
+		(
+			"8/5k2/8/3P4/8/8/6K1/8 w - - 0 1",
+			"8/6k1/8/8/3p4/8/5K2/8 b - - 0 1",
+		),
+	];
+	for (fen, mirrored) in pairs {
+		assert_eq!(evaluate(fen), evaluate(mirrored), "{fen}");
+	}
+}
+
+#[test]
+fn rewards_pawn_structure_and_bishops() {
+	// A passed pawn is worth more than one facing an opposing pawn
+	assert!(
+		evaluate("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1")
+			> evaluate("4k3/3p4/8/8/3P4/8/8/4K3 w - - 0 1") + 100
+	);
+	// Doubled pawns are worth less than pawns next to each other
+	assert!(
+		evaluate("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1")
+			< evaluate("4k3/8/8/8/8/8/2PP4/4K3 w - - 0 1")
+	);
+
+	// Weights loaded from elsewhere are used instead of the built-in ones
+	let board = Board::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+	let params = EvalParams::default();
+	let bishop_pair = params.bishop_pair;
+	let tuned = EvalParams {
+		bishop_pair: bishop_pair + Weight::new(400, 400),
+		..params.clone()
+	};
+	assert_eq!(board.evaluate_with(&params), board.evaluate());
+	assert_eq!(board.evaluate_with(&tuned), board.evaluate() + 400);
+}
+
+#[test]
+fn reads_parameter_files() {
+	let built_in = EvalParams::default();
+	assert_eq!(
+		built_in.material[Pieces::Pawn as usize],
+		Weight::new(82, 94)
+	);
+	assert_eq!(
+		built_in.piece_square[Pieces::Knight as usize][0],
+		Weight::new(-167, -58)
+	);
+
+	let params: EvalParams = "
+		# Only what changes
+		[material]
+		knight = [300, 300] # flat
+
+		[pawns]
+		passed = [
+			1, 2, 3, 4, 5, 6,
+			7, 8, 9, 10, 11, 12,
+		]
+	"
+	.parse()
+	.unwrap();
+	assert_eq!(
+		params.material[Pieces::Knight as usize],
+		Weight::new(300, 300)
+	);
+	assert_eq!(
+		params.material[Pieces::Pawn as usize],
+		built_in.material[Pieces::Pawn as usize]
+	);
+	assert_eq!(params.passed_pawn[5], Weight::new(11, 12));
+}
+
+#[test]
+fn rejects_invalid_parameter_files() {
+	let error = |text: &str| text.parse::<EvalParams>().unwrap_err();
+	assert_eq!(
+		error("[material]\n\nking = [1, 1]"),
+		EvalParamsError {
+			line: 3,
+			kind: EvalParamsErrorKind::UnknownKey(String::from("material.king")),
+		}
+	);
+	assert_eq!(
+		error("[bishops]\npair = [30]").kind,
+		EvalParamsErrorKind::WrongCount {
+			key: String::from("bishops.pair"),
+			expected: 2,
+			found: 1,
+		}
+	);
+	assert_eq!(
+		error("[pawns]\ndoubled = [1, x]").kind,
+		EvalParamsErrorKind::InvalidNumber(String::from("x"))
+	);
+	assert_eq!(
+		error("[pawns]\ndoubled = [1,\n2").kind,
+		EvalParamsErrorKind::UnclosedList
+	);
+	assert_eq!(error("[pawns\n").kind, EvalParamsErrorKind::Syntax);
+}
@@ -0,0 +1,152 @@
+use chess_rules::{Board, FenError, PieceColor, Position};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn error(fen: &str) -> FenError {
+	Board::from_fen(fen).unwrap_err()
+}
+
+#[test]
+fn round_trips() {
+	let fens = [
+		Board::STARTING_FEN,
+		KIWIPETE,
+		"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+		"4k3/8/8/8/3pP3/8/8/4K3 b - e3 12 40",
+	];
+	for fen in fens {
+		assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+	}
+}
+
+#[test]
+fn rejects_bad_placement() {
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"),
+		FenError::WrongRankCount(7)
+	);
+	assert_eq!(
+		error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+		FenError::BadRankLength { rank: 7, length: 7 }
+	);
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/36/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+		FenError::BadRankLength { rank: 4, length: 9 }
+	);
+	// Only 1 to 8 empty squares can be written as a digit
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+		FenError::InvalidPieceSymbol('9')
+	);
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/08/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+		FenError::InvalidPieceSymbol('0')
+	);
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/4x3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+		FenError::InvalidPieceSymbol('x')
+	);
+}
+
+#[test]
+fn rejects_impossible_positions() {
+	assert_eq!(
+		error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+		FenError::KingCount {
+			color: PieceColor::Black,
+			count: 0
+		}
+	);
+	assert_eq!(
+		error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+		FenError::KingCount {
+			color: PieceColor::White,
+			count: 2
+		}
+	);
+	assert_eq!(
+		error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+		FenError::PawnOnBackRank(Position { row: 7, col: 7 })
+	);
+	assert_eq!(
+		error("4k3/8/8/8/8/8/8/p3K3 w - - 0 1"),
+		FenError::PawnOnBackRank(Position { row: 0, col: 0 })
+	);
+
+	// Black's king is attacked with white to move
+	assert_eq!(
+		error("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1"),
+		FenError::OpponentInCheck
+	);
+	assert!(Board::from_fen("4k3/4R3/8/8/8/8/8/4K3 b - - 0 1").is_ok());
+}
+
+#[test]
+fn rejects_bad_castling_rights() {
+	assert_eq!(
+		error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+		FenError::CastlingWithoutPieces('K')
+	);
+	// The rooks are home but the king isn't
+	assert_eq!(
+		error("r3k2r/8/8/8/8/8/8/R4K1R w Q - 0 1"),
+		FenError::CastlingWithoutPieces('Q')
+	);
+	assert_eq!(
+		error("r3k2r/8/8/8/8/8/8/R3K2R w KQkr - 0 1"),
+		FenError::InvalidCastling("KQkr".to_string())
+	);
+	assert_eq!(
+		error("r3k2r/8/8/8/8/8/8/R3K2R w KK - 0 1"),
+		FenError::InvalidCastling("KK".to_string())
+	);
+}
+
+#[test]
+fn rejects_bad_en_passant_squares() {
+	// After 1. e4 the square is e3, which is on the wrong rank for white to take on
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1"),
+		FenError::InvalidEnPassant("e3".to_string())
+	);
+	// Right rank, but no pawn just moved past it
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1"),
+		FenError::InvalidEnPassant("e6".to_string())
+	);
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1"),
+		FenError::InvalidEnPassant("e9".to_string())
+	);
+}
+
+#[test]
+fn rejects_bad_fields() {
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"),
+		FenError::WrongFieldCount(5)
+	);
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 1"),
+		FenError::WrongFieldCount(7)
+	);
+	assert_eq!(error(""), FenError::WrongFieldCount(0));
+	assert_eq!(
+		error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+		FenError::InvalidSideToMove("x".to_string())
+	);
+
+	for halfmove in ["-1", "4294967296", "x"] {
+		assert_eq!(
+			error(&format!("4k3/8/8/8/8/8/8/4K3 w - - {halfmove} 1")),
+			FenError::InvalidHalfmoveClock(halfmove.to_string())
+		);
+	}
+	for fullmove in ["0", "-1", "4294967296"] {
+		assert_eq!(
+			error(&format!("4k3/8/8/8/8/8/8/4K3 w - - 0 {fullmove}")),
+			FenError::InvalidFullmoveNumber(fullmove.to_string())
+		);
+	}
+	assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 4294967295 4294967295").is_ok());
+}
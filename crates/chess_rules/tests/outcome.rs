@@ -0,0 +1,121 @@
+use chess_rules::{Board, GameOverReason, PieceColor};
+
+fn insufficient(fen: &str) -> (bool, bool) {
+	let board = Board::from_fen(fen).unwrap();
+	(
+		board.has_insufficient_material(PieceColor::White),
+		board.has_insufficient_material(PieceColor::Black),
+	)
+}
+
+#[test]
+fn tells_who_can_still_mate() {
+	// A lone king can never mate, a pawn always might
+	assert_eq!(
+		insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"),
+		(false, true)
+	);
+	assert_eq!(
+		insufficient("4k3/8/8/8/8/8/8/3RK3 w - - 0 1"),
+		(false, true)
+	);
+
+	// A knight can mate when the opponent's own pieces block its king in
+	assert_eq!(insufficient("4k3/8/8/8/8/8/8/3NK3 w - - 0 1"), (true, true));
+	assert_eq!(
+		insufficient("4k3/4p3/8/8/8/8/8/3NK3 w - - 0 1"),
+		(false, false)
+	);
+	assert_eq!(
+		insufficient("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1"),
+		(false, true)
+	);
+
+	// Bishops only mate with help from a bishop on the other colour, a knight or a pawn
+	assert_eq!(
+		insufficient("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+		(true, true)
+	);
+	assert_eq!(
+		insufficient("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+		(true, true)
+	);
+	assert_eq!(
+		insufficient("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"),
+		(false, false)
+	);
+	assert_eq!(
+		insufficient("4k3/8/8/8/8/8/8/1BB1K3 w - - 0 1"),
+		(false, true)
+	);
+}
+
+/// Plays `moves` one after the other, returning the key of every position on the way, the first
+/// one included, and whether a draw could be claimed after each move
+fn play(board: &mut Board, moves: &[&str]) -> (Vec<u64>, Vec<Option<GameOverReason>>) {
+	let mut keys = vec![board.zobrist_key()];
+	let mut draws = Vec::new();
+	for uci in moves {
+		let mv = board.parse_uci(uci).unwrap();
+		board.make_move(mv);
+		keys.push(board.zobrist_key());
+		draws.push(board.draw_reason(&keys));
+	}
+	(keys, draws)
+}
+
+#[test]
+fn knight_shuffle_repeats_threefold() {
+	let mut board = Board::starting_position();
+	let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+	let (_, draws) = play(&mut board, &shuffle.repeat(2));
+
+	// The starting position is on the board for the third time with the last move
+	let (last, before) = draws.split_last().unwrap();
+	assert_eq!(*last, Some(GameOverReason::ThreefoldRepetition));
+	assert!(before.iter().all(Option::is_none), "{before:?}");
+}
+
+#[test]
+fn fifty_moves_without_capture_or_pawn_move_draw() {
+	let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 98 80").unwrap();
+	let (_, draws) = play(&mut board, &["a1a2", "e8d8", "e2e4"]);
+	assert_eq!(draws[0], None);
+	assert_eq!(draws[1], Some(GameOverReason::FiftyMoveRule));
+	// A pawn move starts the count over
+	assert_eq!(board.halfmove_clock, 0);
+	assert_eq!(draws[2], None);
+}
+
+#[test]
+fn repetitions_need_the_same_rights() {
+	// After the kings went back and forth once the pieces stand where they started, but no side
+	// can castle anymore, so the starting position itself never comes back
+	let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+	let shuffle = ["e1f1", "e8f8", "f1e1", "f8e8"];
+	let (keys, draws) = play(&mut board, &shuffle.repeat(3));
+	assert_ne!(keys[4], keys[0]);
+	// Counting the start, that placement would be on the board a third time after 8 moves
+	assert_eq!(draws.iter().position(Option::is_some), Some(9));
+
+	// Right after the double push the pawn on d4 can take en passant, later in the same
+	// placement it can't
+	let mut board = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+	let shuffle = ["e8d8", "e1d1", "d8e8", "d1e1"];
+	let moves: Vec<&str> = ["e2e4"].into_iter().chain(shuffle.repeat(3)).collect();
+	let (keys, draws) = play(&mut board, &moves);
+	assert_ne!(keys[5], keys[1]);
+	// Counting the position after the double push it would be a third time after 9 moves
+	assert_eq!(draws.iter().position(Option::is_some), Some(9));
+
+	// The same placement with the other side to move is another position
+	let white = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+	let black = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+	let keys = [
+		black.zobrist_key(),
+		black.zobrist_key(),
+		white.zobrist_key(),
+	];
+	assert_ne!(white.zobrist_key(), black.zobrist_key());
+	assert_eq!(white.draw_reason(&keys), None);
+}
@@ -0,0 +1,72 @@
+//! Node counts for the standard perft positions from the Chess Programming Wiki
+//! (<https://www.chessprogramming.org/Perft_Results>)
+
+use chess_rules::Board;
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+	let board = Board::from_fen(fen).unwrap();
+	for (depth, &nodes) in (1..).zip(expected) {
+		assert_eq!(board.perft(depth), nodes, "depth {depth} of {fen}");
+	}
+}
+
+#[test]
+fn initial_position() {
+	assert_perft(Board::STARTING_FEN, &[20, 400, 8_902, 197_281]);
+}
+
+#[test]
+fn kiwipete() {
+	assert_perft(
+		"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+		&[48, 2_039, 97_862],
+	);
+}
+
+#[test]
+fn position_3() {
+	assert_perft(
+		"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+		&[14, 191, 2_812, 43_238, 674_624],
+	);
+}
+
+#[test]
+fn position_4() {
+	assert_perft(
+		"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+		&[6, 264, 9_467, 422_333],
+	);
+}
+
+#[test]
+fn position_4_mirrored() {
+	assert_perft(
+		"r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+		&[6, 264, 9_467, 422_333],
+	);
+}
+
+#[test]
+fn position_5() {
+	assert_perft(
+		"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+		&[44, 1_486, 62_379],
+	);
+}
+
+#[test]
+fn position_6() {
+	assert_perft(
+		"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+		&[46, 2_079, 89_890],
+	);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+	let board = Board::starting_position();
+	let divide = board.divide(3);
+	assert_eq!(divide.len(), 20);
+	assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8_902);
+}
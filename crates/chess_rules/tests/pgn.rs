@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use chess_rules::{GameResult, PgnErrorKind, PgnGame, SanError};
+
+const GAMES: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+
+[Event "Annotated"]
+[Result "*"]
+
+% This line is escaped and ignored
+1. e4 $1 (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... c5!? ; Sicilian
+2. Nf3 {[%clk 0:04:58]} d6 {[%clk 0:04:57.5]} *
+"#;
+
+#[test]
+fn reads_multiple_games() {
+	let games = PgnGame::parse_all(GAMES).unwrap();
+	assert_eq!(games.len(), 2);
+
+	let fischer = &games[0];
+	assert_eq!(fischer.tag("White"), Some("Fischer, Robert J."));
+	assert_eq!(fischer.result(), Some(GameResult::Draw));
+	assert_eq!(fischer.moves.len(), 85);
+	assert_eq!(
+		fischer.moves[4].comment.as_deref(),
+		Some("This opening is called the Ruy Lopez.")
+	);
+	assert_eq!(
+		fischer.final_board().to_fen(),
+		"8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43"
+	);
+}
+
+#[test]
+fn reads_annotations_and_variations() {
+	let game = &PgnGame::parse_all(GAMES).unwrap()[1];
+	assert_eq!(game.result(), None);
+	assert_eq!(game.moves.len(), 4);
+
+	let e4 = &game.moves[0];
+	assert_eq!(e4.nags, [1]);
+	assert_eq!(e4.variations.len(), 1);
+	let d4 = &e4.variations[0];
+	assert_eq!(d4.len(), 3);
+	assert_eq!(d4[1].variations[0].len(), 2);
+
+	let c5 = &game.moves[1];
+	assert_eq!(c5.nags, [5]);
+	assert_eq!(c5.comment.as_deref(), Some("Sicilian"));
+
+	assert_eq!(game.moves[2].clock(), Some(Duration::from_secs(298)));
+	assert_eq!(game.moves[3].clock(), Some(Duration::from_millis(297_500)));
+}
+
+#[test]
+fn round_trips() {
+	for game in PgnGame::parse_all(GAMES).unwrap() {
+		let written = game.to_string();
+		assert!(written.lines().all(|x| x.len() < 80), "{written}");
+		assert_eq!(PgnGame::parse_all(&written).unwrap(), [game]);
+	}
+}
+
+#[test]
+fn round_trips_comments_before_variations() {
+	let game = PgnGame::parse_all("1. e4 e5 ( {Or the Sicilian} 1... c5 ) 2. Nf3 *").unwrap();
+	let c5 = &game[0].moves[1].variations[0][0];
+	assert_eq!(c5.leading_comment.as_deref(), Some("Or the Sicilian"));
+
+	let written = game[0].to_string();
+	assert!(
+		written.contains("1. e4 e5 ({Or the Sicilian} 1... c5) 2. Nf3 *"),
+		"{written}"
+	);
+	assert_eq!(PgnGame::parse_all(&written).unwrap(), game);
+}
+
+#[test]
+fn writes_clock_comments() {
+	let mut game = PgnGame::default();
+	let mv = game.start.parse_san("e4").unwrap();
+	let mut played = chess_rules::PgnMove::new(mv);
+	played.comment = Some("Best by test".to_string());
+	played.set_clock(Duration::from_secs(3599));
+	played.set_clock(Duration::from_secs(3661));
+	game.moves.push(played);
+	game.set_result(Some(GameResult::WhiteWins));
+
+	let written = game.to_string();
+	assert!(written.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n"));
+	assert!(written.ends_with("\n\n1. e4 {[%clk 1:01:01] Best by test} 1-0\n"));
+}
+
+#[test]
+fn reports_illegal_moves() {
+	let error = PgnGame::parse_all("1. e4 e5 2. Ke3 *").unwrap_err();
+	assert_eq!(error.game, 1);
+	assert_eq!(
+		error.kind,
+		PgnErrorKind::InvalidMove {
+			ply: 3,
+			error: SanError::Illegal("Ke3".to_string())
+		}
+	);
+
+	let error = PgnGame::parse_all("1. e4 * 1. e4 (e5 *").unwrap_err();
+	assert_eq!(error.game, 2);
+}
@@ -0,0 +1,106 @@
+use chess_rules::{Board, SanError};
+
+fn san_of(fen: &str, uci: &str) -> String {
+	let board = Board::from_fen(fen).unwrap();
+	let mv = board
+		.legal_moves()
+		.into_iter()
+		.find(|x| x.to_string() == uci)
+		.unwrap();
+	format!("{:#}", board.san(mv))
+}
+
+#[test]
+fn writes_san() {
+	let cases = [
+		(Board::STARTING_FEN, "g1f3", "Nf3"),
+		(Board::STARTING_FEN, "e2e4", "e4"),
+		// Knights on b8 and f6 can both reach d7
+		(
+			"rnbqkb1r/ppp1pppp/5n2/3p4/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 0 1",
+			"b8d7",
+			"Nbd7",
+		),
+		// Rooks on a1 and a5 share the file, so the rank tells them apart
+		("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+		// Queens on h4, e1 and h1 all reach e4, only the full square picks out the one on h1
+		("8/8/1k6/8/7Q/8/8/K3Q2Q w - - 0 1", "h1e4", "Qh1e4"),
+		("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6 e.p."),
+		("r3k3/8/8/8/8/8/8/3K4 b q - 0 1", "e8c8", "O-O-O+"),
+		("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+"),
+		("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8n", "exd8=N"),
+		(
+			"rnbqkbnr/ppppp2p/5p2/6p1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3",
+			"d1h5",
+			"Qh5#",
+		),
+	];
+
+	for (fen, uci, san) in cases {
+		assert_eq!(san_of(fen, uci), san, "{uci} in {fen}");
+	}
+}
+
+#[test]
+fn en_passant_mark_only_in_alternate_form() {
+	let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+	let mv = board.parse_san("exd6").unwrap();
+	assert_eq!(board.san(mv).to_string(), "exd6");
+}
+
+#[test]
+fn parses_san() {
+	let board =
+		Board::from_fen("rnbqkb1r/ppp1pppp/5n2/3p4/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 0 1").unwrap();
+	assert_eq!(board.parse_san("Nbd7").unwrap().to_string(), "b8d7");
+	assert_eq!(board.parse_san("Nfd7!?").unwrap().to_string(), "f6d7");
+	assert_eq!(board.parse_san("e5").unwrap().to_string(), "e7e5");
+	assert_eq!(
+		board.parse_san("Nd7"),
+		Err(SanError::Ambiguous("Nd7".to_string()))
+	);
+	assert_eq!(
+		board.parse_san("Ke7"),
+		Err(SanError::Illegal("Ke7".to_string()))
+	);
+	assert_eq!(
+		board.parse_san("Zz9"),
+		Err(SanError::Invalid("Zz9".to_string()))
+	);
+
+	let board = Board::from_fen("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(board.parse_san("e8=Q+").unwrap().to_string(), "e7e8q");
+	assert_eq!(board.parse_san("exd8N").unwrap().to_string(), "e7d8n");
+
+	let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+	assert_eq!(board.parse_san("0-0-0").unwrap().to_string(), "e1c1");
+	assert_eq!(board.parse_san("O-O").unwrap().to_string(), "e1g1");
+}
+
+#[test]
+fn capture_marks_must_match() {
+	// The pawn on d5 can only take on e6, it doesn't push there
+	let board = Board::from_fen("4k3/8/4n3/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(board.parse_san("dxe6").unwrap().to_string(), "d5e6");
+	assert_eq!(
+		board.parse_san("e6"),
+		Err(SanError::Illegal("e6".to_string()))
+	);
+
+	let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+	assert_eq!(board.parse_san("e4").unwrap().to_string(), "e2e4");
+	assert_eq!(
+		board.parse_san("exe4"),
+		Err(SanError::Illegal("exe4".to_string()))
+	);
+}
+
+#[test]
+fn san_round_trips() {
+	let board =
+		Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+			.unwrap();
+	for mv in board.legal_moves() {
+		assert_eq!(board.parse_san(&board.san(mv).to_string()), Ok(mv));
+	}
+}
@@ -0,0 +1,170 @@
+use std::{sync::atomic::AtomicBool, time::Duration};
+
+use std::collections::HashSet;
+
+use chess_rules::{Board, CastlingMode, Score, SearchInfo, SearchLimits, Searcher, SkillLevel};
+use strum::IntoEnumIterator;
+
+fn best_move(fen: &str, depth: u8) -> (String, Vec<SearchInfo>) {
+	let board = Board::from_fen(fen).unwrap();
+	let mut infos = Vec::new();
+	let mv = Searcher::new(1)
+		.search(
+			&board,
+			&[],
+			SearchLimits::depth(depth),
+			&AtomicBool::new(false),
+			|x| infos.push(x.clone()),
+		)
+		.unwrap();
+	(mv.to_uci(CastlingMode::Standard).to_string(), infos)
+}
+
+#[test]
+fn finds_mate_in_one() {
+	let (mv, infos) = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+	assert_eq!(mv, "a1a8");
+	assert_eq!(infos.last().unwrap().score, Score::Mate(1));
+}
+
+#[test]
+fn finds_mate_in_two() {
+	// Nf6+ gxf6 Bxf7#
+	let (mv, infos) = best_move(
+		"r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+		5,
+	);
+	assert_eq!(mv, "d5f6");
+	assert_eq!(infos.last().unwrap().score, Score::Mate(2));
+}
+
+#[test]
+fn takes_a_hanging_queen() {
+	let (mv, infos) = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 4);
+	assert_eq!(mv, "d2d5");
+	assert!(matches!(infos.last().unwrap().score, Score::Centipawns(x) if x > 300));
+}
+
+#[test]
+fn reports_every_depth() {
+	let (_, infos) = best_move(Board::STARTING_FEN, 4);
+	let depths: Vec<_> = infos.iter().map(|x| x.depth).collect();
+	assert_eq!(depths, [1, 2, 3, 4]);
+	assert!(infos.last().unwrap().hashfull > 0);
+	assert!(infos
+		.iter()
+		.all(|x| !x.pv.is_empty() && x.pv.len() <= x.depth as usize));
+}
+
+#[test]
+fn has_no_move_without_legal_moves() {
+	let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+	let mv = Searcher::default().search(
+		&board,
+		&[],
+		SearchLimits::depth(3),
+		&AtomicBool::new(false),
+		|_| {},
+	);
+	assert_eq!(mv, None);
+}
+
+#[test]
+fn stops_when_told_to() {
+	let board = Board::starting_position();
+	let stop = AtomicBool::new(true);
+	let mut depths = Vec::new();
+	let mv = Searcher::default().search(&board, &[], SearchLimits::default(), &stop, |x| {
+		depths.push(x.depth)
+	});
+	// The first depth is still finished, so there is a move to play
+	assert!(mv.is_some());
+	assert_eq!(depths, [1]);
+
+	let limits = SearchLimits::for_clock(Duration::from_secs(60), Duration::from_secs(1), None);
+	assert!(
+		limits.time.unwrap() > Duration::from_secs(2)
+			&& limits.time.unwrap() < Duration::from_secs(5)
+	);
+}
+
+fn skilled_move(fen: &str, skill: SkillLevel, seed: u64) -> String {
+	let board = Board::from_fen(fen).unwrap();
+	let mut searcher = Searcher::new(1);
+	searcher.set_skill_level(skill);
+	searcher.seed(seed);
+	let mv = searcher.search(
+		&board,
+		&[],
+		SearchLimits::default(),
+		&AtomicBool::new(false),
+		|_| {},
+	);
+	mv.unwrap().to_uci(CastlingMode::Standard).to_string()
+}
+
+#[test]
+fn tightens_limits_by_skill() {
+	let limits = SearchLimits::move_time(Duration::from_secs(10));
+	let beginner = SkillLevel::Beginner.limit(limits);
+	assert_eq!(beginner.depth, Some(2));
+	assert_eq!(beginner.nodes, Some(5_000));
+	assert_eq!(beginner.time, Some(Duration::from_secs(3)));
+
+	assert_eq!(SkillLevel::Maximum.limit(limits), limits);
+	assert_eq!(
+		SkillLevel::Advanced.limit(SearchLimits::depth(3)).depth,
+		Some(3)
+	);
+}
+
+#[test]
+fn weak_levels_vary_their_moves() {
+	let moves: HashSet<_> = (1..=20)
+		.map(|seed| skilled_move(Board::STARTING_FEN, SkillLevel::Beginner, seed))
+		.collect();
+	assert!(moves.len() > 2, "{moves:?}");
+}
+
+#[test]
+fn every_level_mates_in_one() {
+	for skill in SkillLevel::iter() {
+		for seed in 1..=10 {
+			assert_eq!(
+				skilled_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", skill, seed),
+				"a1a8"
+			);
+		}
+	}
+}
+
+#[test]
+fn reports_several_lines() {
+	let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+	let mut searcher = Searcher::new(1);
+	searcher.set_multi_pv(3);
+	let mut infos = Vec::new();
+	let mv = searcher.search(
+		&board,
+		&[],
+		SearchLimits::depth(3),
+		&AtomicBool::new(false),
+		|x| infos.push(x.clone()),
+	);
+	assert_eq!(
+		mv.unwrap().to_uci(CastlingMode::Standard).to_string(),
+		"a1a8"
+	);
+
+	// A found mate ends the search early
+	let depth = infos.last().unwrap().depth;
+	let last: Vec<_> = infos.iter().filter(|x| x.depth == depth).collect();
+	assert_eq!(
+		last.iter().map(|x| x.multi_pv).collect::<Vec<_>>(),
+		[1, 2, 3]
+	);
+	assert_eq!(last[0].score, Score::Mate(1));
+	assert!(matches!(last[1].score, Score::Centipawns(_)));
+	let first_moves: HashSet<_> = last.iter().map(|x| x.pv[0]).collect();
+	assert_eq!(first_moves.len(), 3);
+}
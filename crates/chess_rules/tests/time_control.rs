@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use chess_rules::{TimeBonus, TimeControl, TimeControlStage};
+
+const fn secs(seconds: u64) -> Duration {
+	Duration::from_secs(seconds)
+}
+
+#[test]
+fn parses_pgn_time_controls() {
+	let time_control: TimeControl = "40/5400:1800+30".parse().unwrap();
+	assert_eq!(
+		time_control.stages,
+		[
+			TimeControlStage {
+				moves: Some(40),
+				time: secs(5400),
+				bonus: TimeBonus::None,
+			},
+			TimeControlStage {
+				moves: None,
+				time: secs(1800),
+				bonus: TimeBonus::Increment(secs(30)),
+			},
+		]
+	);
+
+	assert_eq!(
+		"180+2".parse(),
+		Ok(TimeControl::sudden_death(
+			secs(180),
+			TimeBonus::Increment(secs(2))
+		))
+	);
+	assert_eq!("-".parse(), Ok(TimeControl::unlimited()));
+
+	for time_control in ["40/5400:1800+30", "300", "300+5d", "300+5b", "40/7200", "-"] {
+		let parsed: TimeControl = time_control.parse().unwrap();
+		assert_eq!(parsed.to_string(), time_control);
+	}
+	for invalid in [
+		"",
+		"?",
+		"*180",
+		"300:40/5400",
+		"0/300",
+		"5 min",
+		"300+",
+		"300+5x",
+	] {
+		assert!(invalid.parse::<TimeControl>().is_err(), "{invalid}");
+	}
+}
+
+#[test]
+fn writes_pgn_time_controls() {
+	for (time_control, pgn) in [
+		("40/5400:1800+30", "40/5400:1800+30"),
+		("300+5d", "300"),
+		("40/5400+5b:1800+5d", "40/5400:1800"),
+		("-", "-"),
+	] {
+		let parsed: TimeControl = time_control.parse().unwrap();
+		assert_eq!(parsed.to_pgn(), pgn);
+		assert_eq!(parsed.has_delay(), time_control != pgn);
+	}
+}
+
+#[test]
+fn adds_bonus_time_after_moves() {
+	let increment = TimeControl::sudden_death(secs(180), TimeBonus::Increment(secs(2)));
+	assert_eq!(increment.time_after_move(0, secs(10)), secs(2));
+
+	let bronstein = TimeControl::sudden_death(secs(180), TimeBonus::Bronstein(secs(5)));
+	assert_eq!(bronstein.time_after_move(0, secs(3)), secs(3));
+	assert_eq!(bronstein.time_after_move(0, secs(10)), secs(5));
+
+	let simple = TimeControl::sudden_death(secs(180), TimeBonus::SimpleDelay(secs(5)));
+	assert_eq!(simple.time_after_move(0, secs(10)), Duration::ZERO);
+	assert_eq!(simple.simple_delay(12), secs(5));
+	assert_eq!(increment.simple_delay(12), Duration::ZERO);
+}
+
+#[test]
+fn moves_through_stages() {
+	let time_control: TimeControl = "40/5400:1800+30".parse().unwrap();
+	assert_eq!(time_control.initial_time(), Some(secs(5400)));
+	assert_eq!(time_control.time_after_move(38, secs(1)), Duration::ZERO);
+	// The 40th move brings the time of the second stage
+	assert_eq!(time_control.time_after_move(39, secs(1)), secs(1800));
+	assert_eq!(time_control.time_after_move(40, secs(1)), secs(30));
+	assert_eq!(time_control.stage(500).unwrap().moves, None);
+	assert_eq!(time_control.moves_to_go(0), Some(40));
+	assert_eq!(time_control.moves_to_go(39), Some(1));
+	assert_eq!(time_control.moves_to_go(40), None);
+
+	// A last stage with a move count repeats
+	let repeating: TimeControl = "40/7200:20/3600".parse().unwrap();
+	assert_eq!(repeating.time_after_move(39, secs(1)), secs(3600));
+	assert_eq!(repeating.time_after_move(59, secs(1)), secs(3600));
+	assert_eq!(repeating.time_after_move(69, secs(1)), Duration::ZERO);
+	assert_eq!(repeating.time_after_move(79, secs(1)), secs(3600));
+	assert_eq!(repeating.moves_to_go(65), Some(15));
+
+	let unlimited = TimeControl::unlimited();
+	assert_eq!(unlimited.initial_time(), None);
+	assert_eq!(unlimited.time_after_move(10, secs(1)), Duration::ZERO);
+}
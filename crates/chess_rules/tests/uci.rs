@@ -0,0 +1,64 @@
+use chess_rules::{Board, CastlingMode, Pieces, Position, UciMove, UciMoveError};
+
+const CASTLING_FEN: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+#[test]
+fn parses_and_writes_squares() {
+	let uci: UciMove = "e7e8q".parse().unwrap();
+	assert_eq!(uci.from, Position { row: 6, col: 4 });
+	assert_eq!(uci.to.index(), 60);
+	assert_eq!(uci.promotion, Some(Pieces::Queen));
+	assert_eq!(uci.to_string(), "e7e8q");
+
+	assert_eq!(UciMove::from_indices(12, 28, None).to_string(), "e2e4");
+	for invalid in ["", "e2", "e2e9", "e2e4k", "e2e4Q", "e2-e4"] {
+		assert_eq!(
+			invalid.parse::<UciMove>(),
+			Err(UciMoveError::Invalid(invalid.to_string()))
+		);
+	}
+}
+
+#[test]
+fn finds_legal_moves() {
+	let board = Board::starting_position();
+	let mv = board.parse_uci("g1f3").unwrap();
+	assert_eq!((mv.from, mv.to), (6, 21));
+	assert!(matches!(
+		board.parse_uci("e2e5"),
+		Err(UciMoveError::Illegal(_))
+	));
+
+	let board = Board::from_fen("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+	assert_eq!(
+		board.parse_uci("e7e8n").unwrap().promotion,
+		Some(Pieces::Knight)
+	);
+	assert!(board.parse_uci("e7e8").is_err());
+}
+
+#[test]
+fn reads_both_castling_conventions() {
+	let board = Board::from_fen(CASTLING_FEN).unwrap();
+	for (standard, chess960) in [("e1g1", "e1h1"), ("e1c1", "e1a1")] {
+		let mv = board.parse_uci(standard).unwrap();
+		assert_eq!(board.parse_uci(chess960), Ok(mv));
+		assert_eq!(mv.to_uci(CastlingMode::Standard).to_string(), standard);
+		assert_eq!(mv.to_uci(CastlingMode::Chess960).to_string(), chess960);
+	}
+
+	// Without castling rights the king can't take its own rook either
+	let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+	assert!(board.parse_uci("e1h1").is_err());
+}
+
+#[test]
+fn round_trips_every_legal_move() {
+	let board = Board::from_fen(CASTLING_FEN).unwrap();
+	for mv in board.legal_moves() {
+		for mode in [CastlingMode::Standard, CastlingMode::Chess960] {
+			let uci = mv.to_uci(mode).to_string();
+			assert_eq!(board.parse_uci(&uci), Ok(mv), "{uci}");
+		}
+	}
+}
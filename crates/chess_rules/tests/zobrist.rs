@@ -0,0 +1,76 @@
+//! Keys from the Polyglot book format specification
+//! (<http://hgm.nubati.net/book_format.html>)
+
+use chess_rules::Board;
+
+fn key_after(moves: &[&str]) -> u64 {
+	let mut board = Board::starting_position();
+	for &played in moves {
+		let mv = board
+			.legal_moves()
+			.into_iter()
+			.find(|x| x.to_string() == played)
+			.unwrap();
+		board.make_move(mv);
+	}
+	board.zobrist_key()
+}
+
+#[test]
+fn polyglot_keys() {
+	let cases: [(&[&str], u64); 9] = [
+		(&[], 0x463b_9618_1691_fc9c),
+		(&["e2e4"], 0x823c_9b50_fd11_4196),
+		(&["e2e4", "d7d5"], 0x0756_b944_61c5_0fb0),
+		(&["e2e4", "d7d5", "e4e5"], 0x662f_afb9_65db_29d4),
+		(&["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a4_8b5a_8e47_ff78),
+		(
+			&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"],
+			0x652a_607c_a3f2_42c1,
+		),
+		(
+			&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"],
+			0x00fd_d303_c946_bdd9,
+		),
+		(
+			&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"],
+			0x3c81_23ea_7b06_7637,
+		),
+		(
+			&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"],
+			0x5c3f_9b82_9b27_9560,
+		),
+	];
+
+	for (moves, key) in cases {
+		assert_eq!(key_after(moves), key, "after {moves:?}");
+	}
+}
+
+#[test]
+fn unmake_restores_key() {
+	let mut board =
+		Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+			.unwrap();
+	let key = board.zobrist_key();
+	for mv in board.legal_moves() {
+		let undo_info = board.make_move(mv);
+		board.unmake_move(mv, undo_info);
+		assert_eq!(board.zobrist_key(), key, "{mv}");
+	}
+}
+
+#[test]
+fn fen_round_trip_keeps_key() {
+	let mut board = Board::starting_position();
+	for played in ["e2e4", "c7c5", "g1f3"] {
+		let mv = board
+			.legal_moves()
+			.into_iter()
+			.find(|x| x.to_string() == played)
+			.unwrap();
+		board.make_move(mv);
+	}
+	let reloaded = Board::from_fen(&board.to_fen()).unwrap();
+	assert_eq!(reloaded.zobrist_key(), board.zobrist_key());
+}
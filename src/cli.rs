@@ -0,0 +1,89 @@
+use std::{fs, path::Path, time::Instant};
+
+use anyhow::{bail, Context, Result};
+use chess_rules::{Board, EvalParams, PgnGame, PieceColor};
+
+use crate::{engine::load_eval_params, uci};
+
+/// Runs the subcommand given on the command line instead of opening the window
+pub fn run(args: &[String]) -> Result<()> {
+	match args {
+		[command, rest @ ..] if command == "perft" => perft(rest),
+		[command, rest @ ..] if command == "pgn" => pgn(rest),
+		[command, rest @ ..] if command == "eval" => eval(rest),
+		[command, ..] if command == "uci" => uci::run(),
+		[command, ..] => bail!("unknown subcommand '{command}'"),
+		[] => Ok(())
+	}
+}
+
+/// `chess perft <fen> <depth>` prints the node count below every legal move and the total, in the
+/// same format as other engines so the output can be diffed against theirs
+fn perft(args: &[String]) -> Result<()> {
+	let Some((depth, fen)) = args.split_last() else {
+		bail!("usage: chess perft <fen> <depth>")
+	};
+	let depth: u32 = depth.parse().with_context(|| format!("invalid depth '{depth}'"))?;
+
+	// The FEN may be passed as a single quoted argument or as its six separate fields
+	let fen = fen.join(" ");
+	let board = Board::from_fen(&fen).with_context(|| format!("invalid FEN '{fen}'"))?;
+
+	let start = Instant::now();
+	// At depth 0 the only node is the position itself, there's no first move to split it up by
+	let total = if depth == 0 {
+		board.perft(0)
+	} else {
+		let mut divide: Vec<(String, u64)> =
+			board.divide(depth).into_iter().map(|(mv, nodes)| (mv.to_string(), nodes)).collect();
+		divide.sort();
+
+		for (mv, nodes) in &divide {
+			println!("{mv}: {nodes}");
+		}
+		println!();
+		divide.iter().map(|(_, nodes)| nodes).sum()
+	};
+	println!("Nodes searched: {total}");
+	println!("Time: {:.3}s", start.elapsed().as_secs_f64());
+
+	Ok(())
+}
+
+/// `chess pgn <file>...` checks every game in the files and prints them back in a clean export
+/// format
+fn pgn(paths: &[String]) -> Result<()> {
+	if paths.is_empty() {
+		bail!("usage: chess pgn <file>...")
+	}
+
+	for path in paths {
+		let pgn = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+		let games = PgnGame::parse_all(&pgn).with_context(|| format!("failed to load {path}"))?;
+		for game in games {
+			println!("{game}");
+		}
+	}
+
+	Ok(())
+}
+
+/// `chess eval <fen> [weights file]` prints the static evaluation of a position from white's point
+/// of view, to see what tuning a weight does
+fn eval(args: &[String]) -> Result<()> {
+	let (fen, params) = match args {
+		[fen] => (fen, EvalParams::default()),
+		[fen, path] => (fen, load_eval_params(Path::new(path))?),
+		_ => bail!("usage: chess eval <fen> [weights file]")
+	};
+	let board = Board::from_fen(fen).with_context(|| format!("invalid FEN '{fen}'"))?;
+
+	let score = board.evaluate_with(&params);
+	let white_score = match board.side_to_move {
+		PieceColor::White => score,
+		PieceColor::Black => -score
+	};
+	println!("{:+.2}", f64::from(white_score) / 100.0);
+
+	Ok(())
+}
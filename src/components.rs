@@ -1,10 +1,13 @@
 #![allow(dead_code, unused, clippy::cast_sign_loss)]
 
-use std::{collections::HashMap, string::ToString};
+use std::{fmt, time::Duration};
 
 use anyhow::Result;
 use bevy::prelude::*;
-use strum::{Display, EnumIter, IntoEnumIterator};
+pub use chess_rules::{
+	Board, CastlingRights, GameOverReason, GameResult, Move, PgnGame, PieceColor, Pieces, Position,
+	SkillLevel, TimeBonus, TimeControl
+};
 
 use crate::{BOARD_SIZE, SQUARE_SIZE, WINDOW_SIZE};
 
@@ -20,31 +23,195 @@ impl Coord {
 		let pos: f32 = pos.into();
 		(pos).mul_add(SQUARE_SIZE, -(WINDOW_SIZE / 2.)) + (SQUARE_SIZE / 2.)
 	}
+
+	/// Cursor position as if the board was shown with white at the bottom. With black at the bottom
+	/// the camera is turned around, so the cursor is turned around the window centre with it
+	pub fn orient_cursor(position: Vec2, orientation: PieceColor) -> Vec2 {
+		match orientation {
+			PieceColor::White => position,
+			PieceColor::Black => Vec2::new(WINDOW_SIZE, WINDOW_SIZE + 100.) - position
+		}
+	}
+
+	/// Board square under the cursor, if the cursor is on the board at all
+	#[allow(clippy::cast_possible_truncation)]
+	pub fn from_cursor(position: Vec2) -> Option<Position> {
+		let x = position.x;
+		let y = position.y - 50.;
+
+		((0. ..WINDOW_SIZE).contains(&x) && (0. ..WINDOW_SIZE).contains(&y)).then(|| {
+			Position::new(
+				BOARD_SIZE - 1 - (y / SQUARE_SIZE).floor() as i8,
+				(x / SQUARE_SIZE).floor() as i8
+			)
+		})
+	}
 }
 
+/// The clocks of both players, run by a [`TimeControl`]. A clock's duration grows with the time
+/// it's given during the game, so its elapsed time is the time its player used
 #[derive(Resource)]
 pub struct GameTimers {
 	pub white: Timer,
 	pub black: Timer,
+	pub time_control: TimeControl,
+	/// Moves each side made, indexed by color, to know the stage of the time control they're in
+	pub moves_made: [u32; 2],
+	/// How long the side to move has been thinking about its move
+	pub turn_time: Duration,
 }
 
 impl GameTimers {
-	pub fn new() -> Self {
+	pub fn new(time_control: TimeControl) -> Self {
+		let time = time_control.initial_time().unwrap_or(Duration::MAX);
 		Self {
-			white: Timer::from_seconds(5. * 60., TimerMode::Once),
-			black: Timer::from_seconds(5. * 60., TimerMode::Once),
+			white: Timer::new(time, TimerMode::Once),
+			black: Timer::new(time, TimerMode::Once),
+			time_control,
+			moves_made: [0; 2],
+			turn_time: Duration::ZERO
 		}
 	}
+
+	pub const fn timer(&self, color: PieceColor) -> &Timer {
+		match color {
+			PieceColor::White => &self.white,
+			PieceColor::Black => &self.black
+		}
+	}
+
+	pub fn timer_mut(&mut self, color: PieceColor) -> &mut Timer {
+		match color {
+			PieceColor::White => &mut self.white,
+			PieceColor::Black => &mut self.black
+		}
+	}
+
+	pub fn set_remaining(&mut self, color: PieceColor, remaining: Duration) {
+		let timer = self.timer_mut(color);
+		timer.set_duration(timer.elapsed().saturating_add(remaining));
+	}
+
+	/// Sets both clocks back to the start of the game, without touching which one is running
+	pub fn restart(&mut self) {
+		let time = self.time_control.initial_time().unwrap_or(Duration::MAX);
+		for timer in [&mut self.white, &mut self.black] {
+			timer.set_duration(time);
+			timer.reset();
+		}
+		self.moves_made = [0; 2];
+		self.turn_time = Duration::ZERO;
+	}
+
+	/// Time `color` gets back with each move in the stage of the time control it's in, counting a
+	/// delay as if it was an increment. `None` in games without clocks
+	pub fn increment(&self, color: PieceColor) -> Option<Duration> {
+		let stage = self.time_control.stage(self.moves_made[color as usize])?;
+		Some(match stage.bonus {
+			TimeBonus::None => Duration::ZERO,
+			TimeBonus::Increment(time)
+			| TimeBonus::Bronstein(time)
+			| TimeBonus::SimpleDelay(time) => time
+		})
+	}
+
+	/// Runs the clock of the side to move, once its simple delay for the move has passed
+	pub fn tick(&mut self, delta: Duration) {
+		for color in [PieceColor::White, PieceColor::Black] {
+			if self.timer(color).paused() {
+				continue
+			}
+
+			let delay = self.time_control.simple_delay(self.moves_made[color as usize]);
+			let before = self.turn_time.saturating_sub(delay);
+			self.turn_time += delta;
+			let running = self.turn_time.saturating_sub(delay) - before;
+			self.timer_mut(color).tick(running);
+		}
+	}
+
+	/// Gives `color` the time it earned with the move it just made
+	pub fn complete_move(&mut self, color: PieceColor) {
+		let moves_made = self.moves_made[color as usize];
+		let added = self.time_control.time_after_move(moves_made, self.turn_time);
+		let timer = self.timer_mut(color);
+		timer.set_duration(timer.duration().saturating_add(added));
+
+		self.moves_made[color as usize] += 1;
+		self.turn_time = Duration::ZERO;
+	}
 }
 
 impl Default for GameTimers {
 	fn default() -> Self {
-		Self::new()
+		Self::new(TimeControl::default())
 	}
 }
 
+/// The rules-side board, shared with every system as a resource
+#[derive(Resource, Debug, Clone, Default, Deref, DerefMut)]
+pub struct BoardResource(pub Board);
+
+/// Keys of every position reached so far in the game, including the starting position
 #[derive(Resource, Debug)]
-pub struct BoardResource(pub [Option<Piece>; 64]);
+pub struct PositionHistory(pub Vec<u64>);
+
+impl FromWorld for PositionHistory {
+	fn from_world(world: &mut World) -> Self {
+		let board = world.resource::<BoardResource>();
+		Self(vec![board.zobrist_key()])
+	}
+}
+
+/// A move of the game together with the time the mover had left after playing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayedMove {
+	pub mv: Move,
+	/// Unknown for moves loaded from a PGN without clock comments
+	pub clock: Option<Duration>,
+}
+
+/// Every move played so far and the position they were played from
+#[derive(Resource, Debug, Clone)]
+pub struct GameHistory {
+	pub start: Board,
+	pub moves: Vec<PlayedMove>,
+	/// Taken back moves, the most recently undone one last, until a different move is played
+	pub undone: Vec<PlayedMove>,
+	/// Whether taking a move back also gives back the time spent on it, as chosen in [`GameSetup`]
+	pub refund_clock: bool,
+}
+
+impl GameHistory {
+	/// How often the piece on each square moved to get there, by replaying the moves
+	pub fn amount_moved(&self) -> [u32; 64] {
+		let mut amount_moved = [0; 64];
+		for PlayedMove { mv, .. } in &self.moves {
+			if let Some(square) = mv.en_passant_capture_square() {
+				amount_moved[square as usize] = 0;
+			}
+			if let Some((rook_start, rook_target)) = mv.castling_rook_squares() {
+				amount_moved[rook_target as usize] = amount_moved[rook_start as usize] + 1;
+				amount_moved[rook_start as usize] = 0;
+			}
+			amount_moved[mv.to as usize] = amount_moved[mv.from as usize] + 1;
+			amount_moved[mv.from as usize] = 0;
+		}
+		amount_moved
+	}
+}
+
+impl FromWorld for GameHistory {
+	fn from_world(world: &mut World) -> Self {
+		let board = world.resource::<BoardResource>();
+		Self {
+			start: board.0.clone(),
+			moves: Vec::new(),
+			undone: Vec::new(),
+			refund_clock: true
+		}
+	}
+}
 
 #[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
 pub struct WhiteTimer;
@@ -55,65 +222,169 @@ pub struct BlackTimer;
 #[derive(Event)]
 pub struct TakeEvent;
 
+/// Asks for a legal move to be played on the board, no matter if a player or an engine chose it
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayMoveEvent(pub Move);
+
 #[derive(Default, Event)]
 pub struct MoveEvent(pub Option<Position>);
 
+/// Replaces the game on the board with a game read from a PGN file
+#[derive(Event, Debug, Clone)]
+pub struct LoadGameEvent(pub PgnGame);
+
+/// Starts a new game as chosen in [`GameSetup`]
+#[derive(Default, Event, Debug, Clone, Copy)]
+pub struct NewGameEvent;
+
+/// Asks for the game to be written to a PGN file
+#[derive(Default, Event, Debug, Clone, Copy)]
+pub struct SaveGameEvent;
+
+/// Sets the board, the sprites and the position history up again from [`GameHistory`], after its
+/// moves were changed by undo, redo or loading a game
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestoreHistoryEvent {
+	/// Also set the clocks to the times recorded with the moves
+	pub restore_clocks: bool,
+}
+
 #[derive(Default, Event)]
 pub struct LegalMoveEvent(pub Option<Vec<i8>>);
 
 #[derive(Default, Event)]
 pub struct HoverEvent(pub Option<Position>);
 
-#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
-pub struct LegalMoveMarker;
-
-#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
-pub struct MovedSquare;
+#[derive(Default, Event)]
+pub struct CheckEvent(pub Option<Position>);
 
-#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
-pub struct HoverSquare;
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOverEvent {
+	pub result: GameResult,
+	pub reason: GameOverReason,
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
-pub enum Pieces {
-	King,
-	Queen,
-	Bishop,
-	Knight,
-	Rook,
-	Pawn,
+/// Screen the app is on, from the main menu through the game setup to the game itself
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, States)]
+pub enum AppState {
+	#[default]
+	Menu,
+	Setup,
+	Playing,
+	GameOver,
 }
 
-#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
-pub struct HighlightSquare;
+/// Whether the board of a game is on screen, played on or not
+pub fn in_game() -> impl FnMut(Res<State<AppState>>) -> bool + Clone {
+	|state: Res<State<AppState>>| matches!(state.get(), AppState::Playing | AppState::GameOver)
+}
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, States)]
-pub enum PieceColor {
+/// Who makes the moves of one side
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerKind {
 	#[default]
-	White,
-	Black,
+	Human,
+	/// The built-in engine, playing as well as the level allows
+	Engine(SkillLevel),
+	/// A UCI engine run as another process, by its index in the engines file
+	External(usize),
 }
 
-impl PieceColor {
-	pub const fn not(self) -> Self {
+impl fmt::Display for PlayerKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
-			Self::Black => Self::White,
-			Self::White => Self::Black,
+			Self::Human => write!(f, "Human"),
+			Self::Engine(skill) => write!(f, "Engine ({skill})"),
+			Self::External(index) => write!(f, "External engine {}", index + 1)
 		}
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Position {
-	pub row: i8,
-	pub col: i8,
+/// Everything chosen on the setup screen for the next game
+#[derive(Resource, Debug, Clone)]
+pub struct GameSetup {
+	pub white: PlayerKind,
+	pub black: PlayerKind,
+	pub time_control: TimeControl,
+	pub fen: String,
+	/// Side shown at the bottom of the board
+	pub orientation: PieceColor,
+	/// Whether taking a move back also gives back the time spent on it
+	pub refund_clock: bool,
 }
 
-impl Position {
-	pub const fn new(row: i8, col: i8) -> Self {
-		Self { row, col }
+impl GameSetup {
+	pub const fn player(&self, color: PieceColor) -> PlayerKind {
+		match color {
+			PieceColor::White => self.white,
+			PieceColor::Black => self.black
+		}
 	}
 }
 
+impl Default for GameSetup {
+	fn default() -> Self {
+		Self {
+			white: PlayerKind::Human,
+			black: PlayerKind::Human,
+			time_control: TimeControl::default(),
+			fen: Board::STARTING_FEN.to_string(),
+			orientation: PieceColor::White,
+			refund_clock: true
+		}
+	}
+}
+
+/// Anything spawned for a game, taken down again when going back to the menu
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct GameEntity;
+
+/// Anything spawned for the menu or the setup screen
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct MenuEntity;
+
+#[derive(Resource, Default, Debug)]
+pub struct GameOutcome(pub Option<GameOverEvent>);
+
+/// After a game is over its position can be looked at further, with both sides moved by hand and
+/// no clock running
+#[derive(Resource, Default, Debug, PartialEq, Eq)]
+pub struct Analysis(pub bool);
+
+/// Record of the game as it ended, kept during analysis so the moves tried out afterwards are
+/// never saved as part of it
+#[derive(Resource, Default, Debug)]
+pub struct FinishedGame(pub Option<PgnGame>);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct CheckSquare;
+
+/// Move of a pawn that reached the last rank and waits for the player to pick its new type
+#[derive(Resource, Default, Debug, PartialEq, Eq)]
+pub struct PendingPromotion(pub Option<Move>);
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct PromotionPicker;
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct PromotionChoice {
+	pub piece_type: Pieces,
+	pub pos: Position,
+}
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct LegalMoveMarker;
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct MovedSquare;
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct HoverSquare;
+
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct HighlightSquare;
+
+/// Sprite side of a piece on the board, kept in sync with the rules-side [`Board`]
 #[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
 pub struct Piece {
 	pub pos: Position,
@@ -122,122 +393,12 @@ pub struct Piece {
 	pub color: PieceColor,
 }
 
-impl FromWorld for BoardResource {
-	fn from_world(_: &mut World) -> Self {
-		load_position_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
-			.map_or(Self([None; 64]), Self)
-	}
-}
-
-fn load_position_from_fen(fen: &str) -> Option<[Option<Piece>; 64]> {
-	let mut board = [None; 64];
-
-	let piece_type_from_symbol: HashMap<char, Pieces> = Pieces::iter()
-		.map(|x| {
-			(
-				match x {
-					Pieces::Knight => 'n',
-					_ => x
-						.to_string()
-						.chars()
-						.next()
-						.unwrap()
-						.to_lowercase()
-						.next()
-						.unwrap(),
-				},
-				x,
-			)
-		})
-		.collect();
-
-	let fen_data: Vec<&str> = fen.split(' ').collect();
-	let fen_board: Vec<&str> = fen_data[0].split('/').collect();
-
-	let mut col = 0;
-	let mut row = BOARD_SIZE;
-
-	for row_data in fen_board {
-		col = -1;
-		row -= 1;
-		for char in row_data.chars() {
-			if char.is_ascii_digit() {
-				col += char as i8;
-				if col >= 7 {
-					continue;
-				}
-			} else {
-				col += 1;
-			}
-			let piece_color = if char.is_uppercase() {
-				PieceColor::White
-			} else {
-				PieceColor::Black
-			};
-
-			let lower_char = &char.to_lowercase().to_string().chars().next()?;
-			if piece_type_from_symbol.contains_key(lower_char) {
-				let piece_type = *piece_type_from_symbol.get(lower_char)?;
-
-				board[(row * BOARD_SIZE + col) as usize] = Some(Piece {
-					piece_type,
-					color: piece_color,
-					amount_moved: 0,
-					pos: Position::new(row, col),
-				});
-			};
-		}
-	}
-
-	Some(board)
-}
-
+/// Square of the piece the player picked up
 #[derive(Resource, Debug)]
-pub struct SelectedPiece(pub Option<Piece>);
+pub struct SelectedPiece(pub Option<Position>);
 
 impl FromWorld for SelectedPiece {
 	fn from_world(_: &mut World) -> Self {
 		Self(None)
 	}
 }
-
-#[derive(Resource, Debug, Clone)]
-pub struct MoveData {
-	pub num_squares_to_edge: HashMap<usize, Vec<i8>>,
-	pub direction_offsets: [i8; 8],
-}
-
-impl FromWorld for MoveData {
-	fn from_world(_: &mut World) -> Self {
-		let mut num_squares_to_edge = HashMap::new();
-		for row in 0..BOARD_SIZE {
-			for col in 0..BOARD_SIZE {
-				let num_north = 7 - col;
-				let num_south = col;
-				let num_west = row;
-				let num_east = 7 - row;
-
-				let index = (col * BOARD_SIZE + row) as usize;
-
-				num_squares_to_edge.insert(
-					index,
-					vec![
-						num_north,
-						num_south,
-						num_west,
-						num_east,
-						num_north.min(num_west),
-						num_south.min(num_east),
-						num_north.min(num_east),
-						num_south.min(num_west),
-					],
-				);
-			}
-		}
-
-		Self {
-			num_squares_to_edge,
-			direction_offsets: [8, -8, -1, 1, 7, -7, 9, -9],
-		}
-	}
-}
@@ -0,0 +1,190 @@
+#![allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
+
+use std::{
+	fs,
+	path::Path,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc
+	},
+	time::Duration
+};
+
+use bevy::{
+	prelude::*,
+	tasks::{AsyncComputeTaskPool, Task}
+};
+use anyhow::{Context, Result};
+use chess_rules::{EvalParams, SearchLimits, Searcher};
+use futures_lite::future;
+
+use crate::{
+	components::{
+		Analysis, AppState, BoardResource, GameSetup, GameTimers, Move, NewGameEvent,
+		PendingPromotion, PlayMoveEvent, PlayerKind, PositionHistory
+	},
+	util::error_handler
+};
+
+/// Thinking time per move when the game is played without clocks
+const UNLIMITED_MOVE_TIME: Duration = Duration::from_secs(2);
+/// Evaluation weights used instead of the built-in ones when this file is in the working directory
+pub const EVAL_PARAMS_FILE: &str = "eval.toml";
+
+pub struct EnginePlugin;
+
+impl Plugin for EnginePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Engine>()
+			.add_systems(Startup, load_eval_params_system.pipe(error_handler))
+			.add_systems(
+				Update,
+				(
+					clear_engine_system.run_if(on_event::<NewGameEvent>()),
+					poll_search_system,
+					start_search_system
+						.run_if(in_state(AppState::Playing))
+						.run_if(resource_equals(Analysis(false)))
+						.run_if(resource_equals(PendingPromotion(None)))
+				)
+					.chain()
+			);
+	}
+}
+
+/// The built-in engine. Its searcher is handed to a background task while it thinks, so the board
+/// keeps being drawn, and comes back together with the move it found
+#[derive(Resource)]
+pub struct Engine {
+	/// `None` while a search has it
+	searcher: Option<Searcher>,
+	search: Option<Task<(Searcher, Option<Move>)>>,
+	/// Tells the running search to stop as soon as it can
+	stop: Arc<AtomicBool>,
+	/// Key of the position the running search is about
+	searched_key: u64,
+	/// Key of the position the engine last moved in, until its move is on the board
+	moved_key: Option<u64>
+}
+
+impl Default for Engine {
+	fn default() -> Self {
+		Self {
+			searcher: Some(Searcher::default()),
+			search: None,
+			stop: Arc::default(),
+			searched_key: 0,
+			moved_key: None
+		}
+	}
+}
+
+/// Starts thinking when it's the engine's turn, at the level chosen for its side. The level
+/// narrows down the time the clock allows
+fn start_search_system(
+	mut engine: ResMut<Engine>,
+	board: Res<BoardResource>,
+	setup: Res<GameSetup>,
+	timers: Res<GameTimers>,
+	position_history: Res<PositionHistory>
+) {
+	let key = board.zobrist_key();
+	if engine.moved_key.is_some_and(|x| x == key) {
+		return
+	}
+	engine.moved_key = None;
+
+	let color = board.side_to_move;
+	let PlayerKind::Engine(skill) = setup.player(color) else { return };
+	if engine.search.is_some() {
+		return
+	}
+	let Some(mut searcher) = engine.searcher.take() else { return };
+	searcher.set_skill_level(skill);
+
+	let limits = match timers.increment(color) {
+		Some(increment) => {
+			let moves_to_go = timers.time_control.moves_to_go(timers.moves_made[color as usize]);
+			SearchLimits::for_clock(timers.timer(color).remaining(), increment, moves_to_go)
+		}
+		None => SearchLimits::move_time(UNLIMITED_MOVE_TIME)
+	};
+
+	// The history ends with the current position once the move to it was looked at
+	let keys = position_history.0.strip_suffix(&[key]).unwrap_or(&position_history.0);
+	let keys = keys.to_vec();
+	let board = board.0.clone();
+	let stop = Arc::new(AtomicBool::new(false));
+	engine.stop = stop.clone();
+	engine.searched_key = key;
+	engine.search = Some(AsyncComputeTaskPool::get().spawn(async move {
+		let mv = searcher.search(&board, &keys, limits, &stop, |info| {
+			debug!("depth {} score {} nodes {}", info.depth, info.score, info.nodes);
+		});
+		(searcher, mv)
+	}));
+}
+
+/// Plays the move of a finished search, if the position it was about is still on the board.
+/// Searches about a position that is gone are told to stop
+fn poll_search_system(
+	mut engine: ResMut<Engine>,
+	board: Res<BoardResource>,
+	setup: Res<GameSetup>,
+	state: Res<State<AppState>>,
+	analysis: Res<Analysis>,
+	mut ev_play: EventWriter<PlayMoveEvent>
+) {
+	if engine.search.is_none() {
+		return
+	}
+
+	let key = board.zobrist_key();
+	let wanted = key == engine.searched_key
+		&& *state.get() == AppState::Playing
+		&& !analysis.0
+		&& matches!(setup.player(board.side_to_move), PlayerKind::Engine(_));
+	if !wanted {
+		engine.stop.store(true, Ordering::Relaxed);
+	}
+
+	let Some(search) = engine.search.as_mut() else { return };
+	let Some((searcher, mv)) = future::block_on(future::poll_once(search)) else { return };
+	engine.searcher = Some(searcher);
+	engine.search = None;
+
+	if let (true, Some(mv)) = (wanted, mv) {
+		engine.moved_key = Some(key);
+		ev_play.send(PlayMoveEvent(mv));
+	}
+}
+
+/// Reads evaluation weights from `path`, keeping the built-in ones for any the file leaves out
+pub fn load_eval_params(path: &Path) -> Result<EvalParams> {
+	let text =
+		fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+	text.parse().with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Lets the engine evaluate with the weights in [`EVAL_PARAMS_FILE`], if there is one
+fn load_eval_params_system(mut engine: ResMut<Engine>) -> Result<()> {
+	let path = Path::new(EVAL_PARAMS_FILE);
+	if !path.exists() {
+		return Ok(())
+	}
+
+	let params = load_eval_params(path)?;
+	if let Some(searcher) = engine.searcher.as_mut() {
+		searcher.set_eval_params(params);
+	}
+	Ok(())
+}
+
+/// Forgets the positions of the last game, once the searcher is back
+fn clear_engine_system(mut engine: ResMut<Engine>) {
+	engine.stop.store(true, Ordering::Relaxed);
+	engine.moved_key = None;
+	if let Some(searcher) = engine.searcher.as_mut() {
+		searcher.clear();
+	}
+}
@@ -0,0 +1,468 @@
+#![allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
+
+use std::{
+	fs,
+	io::{BufRead, BufReader, Write},
+	path::{Path, PathBuf},
+	process::{Child, ChildStdin, Command, Stdio},
+	sync::{
+		mpsc::{self, Receiver, Sender, TryRecvError},
+		Mutex, PoisonError
+	},
+	thread,
+	time::{Duration, Instant}
+};
+
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use chess_rules::CastlingMode;
+
+use crate::{
+	components::{
+		Analysis, AppState, BoardResource, GameHistory, GameOverEvent, GameOverReason, GameResult,
+		GameSetup, GameTimers, Move, NewGameEvent, PendingPromotion, PieceColor, PlayMoveEvent,
+		PlayerKind
+	},
+	util::error_handler
+};
+
+/// Commands of the UCI engines that can be picked as players, one per line, when this file is in
+/// the working directory
+pub const ENGINES_FILE: &str = "engines.txt";
+/// Thinking time per move when the game is played without clocks
+const UNLIMITED_MOVE_TIME: Duration = Duration::from_secs(2);
+/// How long an engine gets to start up and answer `uci` and `isready`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time an engine gets on top of what it was told to think, for the pipes and its own slowness,
+/// before it's taken as hung
+const MOVE_GRACE: Duration = Duration::from_secs(5);
+/// How long an engine gets to exit after `quit` before it's killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct ExternalEnginePlugin;
+
+impl Plugin for ExternalEnginePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ExternalEngines>()
+			.init_resource::<ExternalPlayers>()
+			.add_systems(Startup, load_engines_system.pipe(error_handler))
+			.add_systems(OnEnter(AppState::Menu), quit_engines_system)
+			.add_systems(
+				Update,
+				(
+					new_game_system.pipe(error_handler).run_if(on_event::<NewGameEvent>()),
+					spawn_engines_system
+						.pipe(error_handler)
+						.run_if(in_state(AppState::Playing))
+						.run_if(resource_equals(Analysis(false))),
+					poll_engines_system.pipe(error_handler),
+					start_thinking_system
+						.pipe(error_handler)
+						.run_if(in_state(AppState::Playing))
+						.run_if(resource_equals(Analysis(false)))
+						.run_if(resource_equals(PendingPromotion(None)))
+				)
+					.chain()
+			);
+	}
+}
+
+/// How to start a UCI engine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineCommand {
+	/// Shown on the setup screen, the name of the program's file
+	pub name: String,
+	pub program: PathBuf,
+	pub args: Vec<String>
+}
+
+impl EngineCommand {
+	/// A line of [`ENGINES_FILE`], the program followed by its arguments
+	fn parse(line: &str) -> Option<Self> {
+		let mut words = line.split_whitespace();
+		let program = PathBuf::from(words.next()?);
+		let name = program.file_stem().map_or_else(
+			|| program.display().to_string(),
+			|x| x.to_string_lossy().into_owned()
+		);
+
+		Some(Self {
+			name,
+			program,
+			args: words.map(String::from).collect()
+		})
+	}
+}
+
+/// The engines listed in [`ENGINES_FILE`], which [`PlayerKind::External`] points into
+#[derive(Resource, Debug, Default)]
+pub struct ExternalEngines(pub Vec<EngineCommand>);
+
+/// What a running engine was last asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+	/// Sent `uci`, waiting for `uciok`
+	Uci,
+	/// Sent `isready`, waiting for `readyok`
+	IsReady,
+	Ready,
+	/// Sent `go` for the position with this key
+	Thinking(u64),
+	/// Sent `stop` for a position that is gone, its `bestmove` is thrown away
+	Stopping
+}
+
+/// A UCI engine running as a child process. Its commands are written and its output is read on
+/// threads of their own, so an engine that stops reading or writing never blocks a frame
+struct EngineProcess {
+	/// Index of its command in [`ExternalEngines`]
+	index: usize,
+	name: String,
+	/// Taken when the process is dropped, to be waited for on another thread
+	child: Option<Child>,
+	/// Commands for the writer thread, disconnected once it can't write anymore
+	commands: Sender<String>,
+	/// Lines the engine printed, disconnected once it exits. In a mutex only for resources to be
+	/// shared between threads
+	lines: Mutex<Receiver<String>>,
+	stage: Stage,
+	/// When the current stage has to be over, if ever
+	deadline: Option<Instant>
+}
+
+impl EngineProcess {
+	fn spawn(index: usize, command: &EngineCommand) -> Result<Self> {
+		let mut child = Command::new(&command.program)
+			.args(&command.args)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.spawn()
+			.with_context(|| format!("failed to start {}", command.program.display()))?;
+		let stdin = child.stdin.take().context("no stdin for the engine")?;
+		let stdout = child.stdout.take().context("no stdout for the engine")?;
+
+		let (sender, lines) = mpsc::channel();
+		thread::spawn(move || {
+			for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+				if sender.send(line).is_err() {
+					break
+				}
+			}
+		});
+		let (commands, receiver) = mpsc::channel();
+		thread::spawn(move || write_commands(stdin, receiver));
+
+		let mut process = Self {
+			index,
+			name: command.name.clone(),
+			child: Some(child),
+			commands,
+			lines: Mutex::new(lines),
+			stage: Stage::Uci,
+			deadline: Instant::now().checked_add(HANDSHAKE_TIMEOUT)
+		};
+		process.send("uci")?;
+		Ok(process)
+	}
+
+	fn send(&mut self, command: &str) -> Result<()> {
+		self.commands
+			.send(command.to_string())
+			.ok()
+			.with_context(|| format!("failed to send '{command}' to {}", self.name))
+	}
+
+	fn set_stage(&mut self, stage: Stage, timeout: Duration) {
+		self.stage = stage;
+		self.deadline = Instant::now().checked_add(timeout);
+	}
+
+	/// Asks whether the engine is done setting up, after `ucinewgame` for instance
+	fn sync(&mut self) -> Result<()> {
+		self.send("isready")?;
+		self.set_stage(Stage::IsReady, HANDSHAKE_TIMEOUT);
+		Ok(())
+	}
+}
+
+impl Drop for EngineProcess {
+	fn drop(&mut self) {
+		let _ = self.send("quit");
+		if let Some(child) = self.child.take() {
+			thread::spawn(move || end_process(child));
+		}
+	}
+}
+
+/// Writes the commands sent to an engine until the process is dropped or the engine stops
+/// reading. Closing its input then also tells an engine that missed `quit` to exit
+fn write_commands(mut stdin: ChildStdin, commands: Receiver<String>) {
+	for command in commands {
+		if writeln!(stdin, "{command}").and_then(|()| stdin.flush()).is_err() {
+			break
+		}
+	}
+}
+
+/// Gives an engine that was told to quit [`QUIT_TIMEOUT`] to exit, then kills it to never leave
+/// it running. Killing it also frees a writer thread stuck on its full input
+fn end_process(mut child: Child) {
+	let deadline = Instant::now() + QUIT_TIMEOUT;
+	while matches!(child.try_wait(), Ok(None)) {
+		if Instant::now() > deadline {
+			let _ = child.kill();
+			let _ = child.wait();
+			return
+		}
+		thread::sleep(Duration::from_millis(10));
+	}
+}
+
+/// The engine process playing each side, indexed by color
+#[derive(Resource, Default)]
+pub struct ExternalPlayers {
+	processes: [Option<EngineProcess>; 2],
+	/// Key of the position an engine last moved in, until its move is on the board
+	moved_key: Option<u64>
+}
+
+/// Reads [`ENGINES_FILE`], if there is one
+fn load_engines_system(mut engines: ResMut<ExternalEngines>) -> Result<()> {
+	let path = Path::new(ENGINES_FILE);
+	if !path.exists() {
+		return Ok(())
+	}
+
+	let text =
+		fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+	engines.0 = text
+		.lines()
+		.map(|x| x.split_once('#').map_or(x, |x| x.0))
+		.filter_map(EngineCommand::parse)
+		.collect();
+	Ok(())
+}
+
+/// Engines that keep their side start a new game, the others are quit
+fn new_game_system(mut players: ResMut<ExternalPlayers>, setup: Res<GameSetup>) -> Result<()> {
+	players.moved_key = None;
+	let [white, black] = [PieceColor::White, PieceColor::Black].map(|color| {
+		let slot = &mut players.processes[color as usize];
+		let Some(process) = slot else { return Ok(()) };
+		if setup.player(color) != PlayerKind::External(process.index) {
+			*slot = None;
+			return Ok(())
+		}
+
+		let restarted = process.send("ucinewgame").and_then(|()| process.sync());
+		if restarted.is_err() {
+			*slot = None;
+		}
+		restarted
+	});
+	white.and(black)
+}
+
+/// Starts the engines picked for the game that aren't running yet. One that can't be started
+/// loses the game
+fn spawn_engines_system(
+	mut players: ResMut<ExternalPlayers>,
+	engines: Res<ExternalEngines>,
+	setup: Res<GameSetup>,
+	mut ev_game_over: EventWriter<GameOverEvent>
+) -> Result<()> {
+	let [white, black] = [PieceColor::White, PieceColor::Black].map(|color| {
+		let PlayerKind::External(index) = setup.player(color) else { return Ok(()) };
+		let slot = &mut players.processes[color as usize];
+		if slot.is_some() {
+			return Ok(())
+		}
+
+		let spawned = engines
+			.0
+			.get(index)
+			.context("no such engine in the engines file")
+			.and_then(|command| EngineProcess::spawn(index, command));
+		match spawned {
+			Ok(process) => {
+				*slot = Some(process);
+				Ok(())
+			}
+			Err(err) => {
+				ev_game_over.send(abandoned_by(color));
+				Err(err)
+			}
+		}
+	});
+	white.and(black)
+}
+
+/// Reads what the engines printed. A `bestmove` for the position on the board is played, one
+/// for a position that is gone is dropped. An engine that exits, stops answering or sends an
+/// illegal move is quit and, if a game is being played, loses it
+fn poll_engines_system(
+	mut players: ResMut<ExternalPlayers>,
+	board: Res<BoardResource>,
+	setup: Res<GameSetup>,
+	state: Res<State<AppState>>,
+	analysis: Res<Analysis>,
+	mut ev_play: EventWriter<PlayMoveEvent>,
+	mut ev_game_over: EventWriter<GameOverEvent>
+) -> Result<()> {
+	let key = board.zobrist_key();
+	let playing = *state.get() == AppState::Playing && !analysis.0;
+
+	let [white, black] = [PieceColor::White, PieceColor::Black].map(|color| {
+		let Some(process) = &mut players.processes[color as usize] else { return Ok(()) };
+		let wanted = playing
+			&& board.side_to_move == color
+			&& process.stage == Stage::Thinking(key)
+			&& setup.player(color) == PlayerKind::External(process.index);
+
+		let mut played = None;
+		let result = poll_engine(process, wanted, &board, &mut played);
+		if let Some(mv) = played {
+			players.moved_key = Some(key);
+			ev_play.send(PlayMoveEvent(mv));
+		}
+		if result.is_err() {
+			players.processes[color as usize] = None;
+			if playing {
+				ev_game_over.send(abandoned_by(color));
+			}
+		}
+		result
+	});
+	white.and(black)
+}
+
+/// Handles every line an engine printed since the last frame, putting a move it played for the
+/// position on the board into `played`
+fn poll_engine(
+	process: &mut EngineProcess,
+	wanted: bool,
+	board: &BoardResource,
+	played: &mut Option<Move>
+) -> Result<()> {
+	if matches!(process.stage, Stage::Thinking(_)) && !wanted {
+		process.send("stop")?;
+		process.set_stage(Stage::Stopping, MOVE_GRACE);
+	}
+
+	loop {
+		let lines = process.lines.get_mut().unwrap_or_else(PoisonError::into_inner);
+		let line = match lines.try_recv() {
+			Ok(line) => line,
+			Err(TryRecvError::Empty) => break,
+			Err(TryRecvError::Disconnected) => bail!("{} exited", process.name)
+		};
+		let mut words = line.split_whitespace();
+
+		match (words.next(), process.stage) {
+			(Some("uciok"), Stage::Uci) => process.sync()?,
+			(Some("readyok"), Stage::IsReady) => process.set_stage(Stage::Ready, Duration::MAX),
+			(Some("bestmove"), Stage::Stopping) => process.set_stage(Stage::Ready, Duration::MAX),
+			(Some("bestmove"), Stage::Thinking(_)) => {
+				let uci = words.next().unwrap_or_default();
+				let mv = board
+					.parse_uci(uci)
+					.with_context(|| format!("{} played the illegal move '{uci}'", process.name))?;
+				*played = Some(mv);
+				process.set_stage(Stage::Ready, Duration::MAX);
+			}
+			_ => {}
+		}
+	}
+
+	let overdue = process.deadline.is_some_and(|x| Instant::now() > x);
+	if process.stage != Stage::Ready && overdue {
+		bail!("{} stopped answering", process.name);
+	}
+	Ok(())
+}
+
+/// Sends the game so far and both clocks to the engine whose turn it is
+fn start_thinking_system(
+	mut players: ResMut<ExternalPlayers>,
+	board: Res<BoardResource>,
+	setup: Res<GameSetup>,
+	history: Res<GameHistory>,
+	timers: Res<GameTimers>,
+	mut ev_game_over: EventWriter<GameOverEvent>
+) -> Result<()> {
+	let key = board.zobrist_key();
+	if players.moved_key.is_some_and(|x| x == key) {
+		return Ok(())
+	}
+	players.moved_key = None;
+
+	let color = board.side_to_move;
+	let slot = &mut players.processes[color as usize];
+	let Some(process) = slot else { return Ok(()) };
+	if process.stage != Stage::Ready || setup.player(color) != PlayerKind::External(process.index) {
+		return Ok(())
+	}
+
+	let started = start_thinking(process, key, &history, &timers, color);
+	if started.is_err() {
+		*slot = None;
+		ev_game_over.send(abandoned_by(color));
+	}
+	started
+}
+
+fn start_thinking(
+	process: &mut EngineProcess,
+	key: u64,
+	history: &GameHistory,
+	timers: &GameTimers,
+	color: PieceColor
+) -> Result<()> {
+	let moves: Vec<_> = history
+		.moves
+		.iter()
+		.map(|x| x.mv.to_uci(CastlingMode::Standard).to_string())
+		.collect();
+	let mut position = format!("position fen {}", history.start.to_fen());
+	if !moves.is_empty() {
+		position = format!("{position} moves {}", moves.join(" "));
+	}
+	process.send(&position)?;
+
+	let (go, timeout) = match timers.increment(color) {
+		Some(increment) => {
+			let millis = |x: Duration| x.as_millis();
+			let (white, black) = (PieceColor::White, PieceColor::Black);
+			let mut go = format!(
+				"go wtime {} btime {} winc {} binc {}",
+				millis(timers.timer(white).remaining()),
+				millis(timers.timer(black).remaining()),
+				millis(timers.increment(white).unwrap_or_default()),
+				millis(timers.increment(black).unwrap_or_default())
+			);
+			let moves_made = timers.moves_made[color as usize];
+			if let Some(moves_to_go) = timers.time_control.moves_to_go(moves_made) {
+				go = format!("{go} movestogo {moves_to_go}");
+			}
+			(go, timers.timer(color).remaining() + increment)
+		}
+		None => (format!("go movetime {}", UNLIMITED_MOVE_TIME.as_millis()), UNLIMITED_MOVE_TIME)
+	};
+	process.send(&go)?;
+	process.set_stage(Stage::Thinking(key), timeout.saturating_add(MOVE_GRACE));
+	Ok(())
+}
+
+/// Quits every engine when going back to the menu
+fn quit_engines_system(mut players: ResMut<ExternalPlayers>) {
+	players.processes = [None, None];
+	players.moved_key = None;
+}
+
+const fn abandoned_by(color: PieceColor) -> GameOverEvent {
+	GameOverEvent {
+		result: GameResult::win_for(color.not()),
+		reason: GameOverReason::Abandoned
+	}
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+use crate::{
+	components::{
+		Analysis, AppState, BoardResource, CheckEvent, GameOutcome, GameOverEvent, GameOverReason,
+		GameResult, GameSetup, GameTimers, MoveEvent, PieceColor, PlayerKind, Position,
+		PositionHistory
+	}
+};
+
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				evaluate_position_system.run_if(in_state(AppState::Playing)),
+				// Clocks don't run during analysis, and one that fell already would end it at once
+				flag_fall_system
+					.run_if(in_state(AppState::Playing))
+					.run_if(resource_equals(Analysis(false))),
+				resign_or_agree_draw_system
+					.run_if(in_state(AppState::Playing))
+					.run_if(resource_equals(Analysis(false))),
+				game_over_system
+			)
+				.chain()
+		);
+	}
+}
+
+/// Looks at the position after every move and reports check, checkmate, stalemate and draws for
+/// the side that now has to move
+fn evaluate_position_system(
+	mut ev_move: EventReader<MoveEvent>,
+	mut ev_check: EventWriter<CheckEvent>,
+	mut ev_game_over: EventWriter<GameOverEvent>,
+	board: Res<BoardResource>,
+	mut history: ResMut<PositionHistory>
+) {
+	for event in ev_move.iter() {
+		if event.0.is_none() {
+			continue
+		}
+		let side_to_move = board.side_to_move;
+
+		let in_check = board.is_in_check(side_to_move);
+		let can_move = board.has_legal_moves();
+
+		if in_check {
+			let king_position = board.king_square(side_to_move).map(Position::from_index);
+			ev_check.send(CheckEvent(king_position));
+		} else {
+			ev_check.send(CheckEvent::default());
+		}
+
+		history.0.push(board.zobrist_key());
+
+		// Mate takes precedence over any of the draws, since the game ended with that move
+		let draw_reason = board.draw_reason(&history.0);
+
+		if !can_move {
+			ev_game_over.send(if in_check {
+				GameOverEvent {
+					result: GameResult::win_for(side_to_move.not()),
+					reason: GameOverReason::Checkmate
+				}
+			} else {
+				GameOverEvent {
+					result: GameResult::Draw,
+					reason: GameOverReason::Stalemate
+				}
+			});
+		} else if let Some(reason) = draw_reason {
+			ev_game_over.send(GameOverEvent {
+				result: GameResult::Draw,
+				reason
+			});
+		}
+	}
+}
+
+/// A player whose clock ran out loses, unless the opponent couldn't have mated them anyway
+fn flag_fall_system(
+	timers: Res<GameTimers>,
+	board: Res<BoardResource>,
+	mut ev_game_over: EventWriter<GameOverEvent>
+) {
+	let Some(flagged) = [PieceColor::White, PieceColor::Black]
+		.into_iter()
+		.find(|&x| timers.timer(x).finished())
+	else {
+		return
+	};
+
+	let opponent = flagged.not();
+	ev_game_over.send(if board.has_insufficient_material(opponent) {
+		GameOverEvent {
+			result: GameResult::Draw,
+			reason: GameOverReason::TimeoutVsInsufficientMaterial
+		}
+	} else {
+		GameOverEvent {
+			result: GameResult::win_for(opponent),
+			reason: GameOverReason::Timeout
+		}
+	});
+}
+
+/// Ctrl+R resigns for the player at the board, Ctrl+D ends the game in a draw both players agreed
+/// on. The player at the board is the side to move, unless an engine plays that side. Engines have
+/// to agree to the draw themselves
+fn resign_or_agree_draw_system(
+	keys: Res<Input<KeyCode>>,
+	board: Res<BoardResource>,
+	setup: Res<GameSetup>,
+	mut ev_game_over: EventWriter<GameOverEvent>
+) {
+	if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+		return
+	}
+
+	if keys.just_pressed(KeyCode::R) {
+		let side_to_move = board.side_to_move;
+		let resigning = if setup.player(side_to_move) == PlayerKind::Human {
+			side_to_move
+		} else {
+			side_to_move.not()
+		};
+		ev_game_over.send(GameOverEvent {
+			result: GameResult::win_for(resigning.not()),
+			reason: GameOverReason::Resignation
+		});
+	} else if keys.just_pressed(KeyCode::D) && agrees_to_draw(&board, &setup) {
+		ev_game_over.send(GameOverEvent {
+			result: GameResult::Draw,
+			reason: GameOverReason::Agreement
+		});
+	}
+}
+
+/// Whether the engines of the game take a draw offer. The built-in one does when it doesn't think
+/// it's better, external engines can't be asked and never do
+fn agrees_to_draw(board: &BoardResource, setup: &GameSetup) -> bool {
+	[PieceColor::White, PieceColor::Black].into_iter().all(|color| match setup.player(color) {
+		PlayerKind::Human => true,
+		PlayerKind::Engine(_) => {
+			let score = board.evaluate();
+			let own_score = if board.side_to_move == color { score } else { -score };
+			own_score <= 0
+		}
+		PlayerKind::External(_) => false
+	})
+}
+
+fn game_over_system(
+	mut ev_game_over: EventReader<GameOverEvent>,
+	mut outcome: ResMut<GameOutcome>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut timers: ResMut<GameTimers>
+) {
+	if let Some(event) = ev_game_over.iter().next() {
+		info!("{} ({})", event.result, event.reason);
+		outcome.0 = Some(*event);
+		next_state.set(AppState::GameOver);
+		timers.white.pause();
+		timers.black.pause();
+	}
+}
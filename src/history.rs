@@ -0,0 +1,147 @@
+#![allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
+
+use bevy::prelude::*;
+
+use crate::{
+	components::{
+		in_game, Analysis, AppState, BoardResource, CheckEvent, GameHistory, GameOutcome,
+		GameSetup, GameTimers, LegalMoveEvent, MoveEvent, PendingPromotion, Piece, PieceColor,
+		PlayerKind, Position, PositionHistory, RestoreHistoryEvent, SelectedPiece
+	},
+	piece::{end_turn, spawn_piece_sprites}
+};
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(undo_redo_system, restore_history_system)
+				.chain()
+				.run_if(in_game())
+		);
+	}
+}
+
+/// Ctrl+Z takes the last move back, Ctrl+Y plays the last taken back move again. Against an engine
+/// both go on to the next position where a human is to move, since the engine would otherwise play
+/// the taken back move again at once
+fn undo_redo_system(
+	keys: Res<Input<KeyCode>>,
+	setup: Res<GameSetup>,
+	analysis: Res<Analysis>,
+	mut history: ResMut<GameHistory>,
+	mut ev_restore: EventWriter<RestoreHistoryEvent>
+) {
+	let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+	if !ctrl {
+		return
+	}
+
+	// With engines on both sides nobody waits for a turn, so single moves are taken back
+	let colors = [PieceColor::White, PieceColor::Black];
+	let has_human = colors.into_iter().any(|x| setup.player(x) == PlayerKind::Human);
+	let human_to_move =
+		|color: PieceColor| analysis.0 || !has_human || setup.player(color) == PlayerKind::Human;
+
+	let changed = if keys.just_pressed(KeyCode::Z) {
+		let Some(played) = history.moves.pop() else { return };
+		let mut to_move = played.mv.piece.color;
+		history.undone.push(played);
+		while !human_to_move(to_move) {
+			let Some(played) = history.moves.pop() else { break };
+			to_move = played.mv.piece.color;
+			history.undone.push(played);
+		}
+		true
+	} else if keys.just_pressed(KeyCode::Y) {
+		let Some(played) = history.undone.pop() else { return };
+		let mut to_move = played.mv.piece.color.not();
+		history.moves.push(played);
+		while !human_to_move(to_move) {
+			let Some(played) = history.undone.pop() else { break };
+			to_move = played.mv.piece.color.not();
+			history.moves.push(played);
+		}
+		true
+	} else {
+		false
+	};
+
+	if changed {
+		ev_restore.send(RestoreHistoryEvent {
+			restore_clocks: history.refund_clock
+		});
+	}
+}
+
+/// Replays the game history from its start and puts everything on screen where the last move left
+/// it, ready to play on from there
+fn restore_history_system(
+	mut ev_restore: EventReader<RestoreHistoryEvent>,
+	mut commands: Commands,
+	mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+	pieces: Query<Entity, With<Piece>>,
+	history: Res<GameHistory>,
+	mut board: ResMut<BoardResource>,
+	mut position_history: ResMut<PositionHistory>,
+	mut timers: ResMut<GameTimers>,
+	mut selected_piece: ResMut<SelectedPiece>,
+	mut promotion: ResMut<PendingPromotion>,
+	mut outcome: ResMut<GameOutcome>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut ev_move: EventWriter<MoveEvent>,
+	mut ev_check: EventWriter<CheckEvent>,
+	mut ev_legal: EventWriter<LegalMoveEvent>
+) {
+	let Some(event) = ev_restore.iter().last() else { return };
+
+	for entity in pieces.iter() {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	// Without giving the time back the clocks keep what they have, but the moves are still counted
+	// again since the stage of the time control depends on them
+	let kept_clocks = (!event.restore_clocks).then(|| (timers.white.clone(), timers.black.clone()));
+	timers.restart();
+
+	let mut new_board = history.start.clone();
+	let mut keys = vec![new_board.zobrist_key()];
+	for played in &history.moves {
+		let color = played.mv.piece.color;
+		timers.complete_move(color);
+		if let Some(clock) = played.clock {
+			timers.set_remaining(color, clock);
+		}
+
+		new_board.make_move(played.mv);
+		keys.push(new_board.zobrist_key());
+	}
+
+	if let Some((white, black)) = kept_clocks {
+		timers.white = white;
+		timers.black = black;
+	}
+
+	spawn_piece_sprites(&mut commands, &mut texture_atlases, &new_board, &history.amount_moved());
+	end_turn(new_board.side_to_move.not(), &mut timers);
+	board.0 = new_board;
+
+	selected_piece.0 = None;
+	promotion.0 = None;
+	outcome.0 = None;
+	next_state.set(AppState::Playing);
+	ev_legal.send(LegalMoveEvent::default());
+	ev_check.send(CheckEvent::default());
+
+	// The position after the last move is looked at like after any other move, which also adds
+	// its key to the history again
+	if let Some(last) = history.moves.last() {
+		keys.pop();
+		ev_move.send(MoveEvent(Some(Position::from_index(last.mv.to))));
+	} else {
+		ev_move.send(MoveEvent::default());
+	}
+	position_history.0 = keys;
+}
@@ -7,29 +7,54 @@
 )]
 
 use anyhow::Result;
-use bevy::{app::AppExit, prelude::*, sprite::Anchor, window::PresentMode};
+use bevy::{prelude::*, sprite::Anchor, window::PresentMode};
 use bevy_prototype_lyon::prelude::*;
 use binary::{BinaryPlugin, FONT_HANDLE, PIECE_HANDLE};
 use chrono::Duration;
 use components::{
-	BlackTimer, BoardResource, Coord, GameTimers, HighlightSquare, HoverEvent, HoverSquare,
-	LegalMoveEvent, MoveData, MoveEvent, MovedSquare, Piece, PieceColor, Position, SelectedPiece,
-	TakeEvent, WhiteTimer
+	in_game, Analysis, AppState, BlackTimer, BoardResource, CheckEvent, CheckSquare, Coord,
+	FinishedGame, GameEntity, GameHistory, GameOutcome, GameOverEvent, GameTimers, HighlightSquare,
+	HoverEvent, HoverSquare, LegalMoveEvent, LoadGameEvent, MoveEvent, MovedSquare, NewGameEvent,
+	PendingPromotion, Piece, PieceColor, PlayMoveEvent, Position, PositionHistory,
+	RestoreHistoryEvent, SaveGameEvent, SelectedPiece, TakeEvent, WhiteTimer
 };
+use engine::EnginePlugin;
+use external::ExternalEnginePlugin;
+use game::GamePlugin;
+use history::HistoryPlugin;
+use menu::{despawn_all, start_game_system, MenuPlugin};
 use num_traits::cast::ToPrimitive;
-use piece::PiecePlugin;
+use pgn::PgnPlugin;
+use piece::{spawn_piece_sprites, PiecePlugin};
+use promotion::PromotionPlugin;
 use sounds::SoundPlugin;
-use util::{error_handler, option_handler, BOARD_SIZE, SQUARE_SIZE, WINDOW_SIZE};
+use util::{
+	error_handler, option_handler, piece_texture_atlas, BOARD_SIZE, SQUARE_SIZE, WINDOW_SIZE
+};
 
 use crate::util::macros::{spawn_sprite_bundle, spawn_text_bundle};
 
 mod binary;
+mod cli;
 mod components;
+mod engine;
+mod external;
+mod game;
+mod history;
+mod menu;
+mod pgn;
 mod piece;
+mod promotion;
 mod sounds;
+mod uci;
 mod util;
 
-fn main() {
+fn main() -> Result<()> {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if !args.is_empty() {
+		return cli::run(&args)
+	}
+
 	#[cfg(not(debug_assertions))]
 	std::env::set_var("RUST_LOG", "");
 
@@ -50,38 +75,64 @@ fn main() {
 		.insert_resource(Msaa::Sample8)
 		.init_resource::<BoardResource>()
 		.init_resource::<SelectedPiece>()
-		.init_resource::<MoveData>()
 		.init_resource::<GameTimers>()
-		.add_state::<PieceColor>()
+		.init_resource::<GameOutcome>()
+		.init_resource::<PendingPromotion>()
+		.init_resource::<PositionHistory>()
+		.init_resource::<GameHistory>()
+		.init_resource::<Analysis>()
+		.init_resource::<FinishedGame>()
+		.add_state::<AppState>()
+		.add_event::<PlayMoveEvent>()
 		.add_event::<MoveEvent>()
 		.add_event::<TakeEvent>()
 		.add_event::<HoverEvent>()
 		.add_event::<LegalMoveEvent>()
+		.add_event::<CheckEvent>()
+		.add_event::<GameOverEvent>()
+		.add_event::<LoadGameEvent>()
+		.add_event::<RestoreHistoryEvent>()
+		.add_event::<NewGameEvent>()
+		.add_event::<SaveGameEvent>()
 		.add_systems(
-			Startup,
+			Update,
 			(
-				setup_camera,
+				despawn_all::<GameEntity>,
+				start_game_system.pipe(error_handler),
 				spawn_board_system.pipe(option_handler),
 				spawn_piece_sprites_system.pipe(error_handler),
 				spawn_timers_system
 			)
+				.chain()
+				.run_if(on_event::<NewGameEvent>())
 		)
+		.add_systems(Startup, setup_camera)
 		.add_systems(
 			Update,
 			(
-				update_white_timer_system.pipe(error_handler),
-				update_black_timer_system.pipe(error_handler),
+				update_white_timer_system.pipe(error_handler).run_if(in_game()),
+				update_black_timer_system.pipe(error_handler).run_if(in_game()),
 				countdown
+					.run_if(in_state(AppState::Playing))
+					.run_if(resource_equals(Analysis(false)))
 			)
 		)
 		.add_plugins(SoundPlugin)
 		.add_plugins(PiecePlugin)
+		.add_plugins(GamePlugin)
+		.add_plugins(PromotionPlugin)
+		.add_plugins(PgnPlugin)
+		.add_plugins(HistoryPlugin)
+		.add_plugins(MenuPlugin)
+		.add_plugins(EnginePlugin)
+		.add_plugins(ExternalEnginePlugin)
 		.run();
+
+	Ok(())
 }
 
-fn setup_camera(mut commands: Commands, mut countdown: ResMut<GameTimers>) {
+fn setup_camera(mut commands: Commands) {
 	commands.spawn(Camera2dBundle::default());
-	countdown.black.pause();
 }
 
 fn spawn_board_system(mut commands: Commands) -> Option<()> {
@@ -107,7 +158,8 @@ fn spawn_board_system(mut commands: Commands) -> Option<()> {
 			spawn_sprite_bundle!(
 				commands,
 				color,
-				Vec3::new(Coord::to_win(col, -0.5), Coord::to_win(row, -0.5), 0.0)
+				Vec3::new(Coord::to_win(col, -0.5), Coord::to_win(row, -0.5), 0.0),
+				GameEntity
 			);
 			if row == 1 {
 				spawn_text_bundle!(
@@ -119,7 +171,8 @@ fn spawn_board_system(mut commands: Commands) -> Option<()> {
 						Coord::to_win(row, -1.) + 67.,
 						1.,
 					),
-					text_style.clone()
+					text_style.clone(),
+					GameEntity
 				);
 			}
 			if col == 1 {
@@ -132,7 +185,8 @@ fn spawn_board_system(mut commands: Commands) -> Option<()> {
 						Coord::to_win(row, -1.) + 10.,
 						1.,
 					),
-					text_style.clone()
+					text_style.clone(),
+					GameEntity
 				);
 			}
 		}
@@ -144,62 +198,29 @@ fn spawn_board_system(mut commands: Commands) -> Option<()> {
 fn spawn_piece_sprites_system(
 	mut commands: Commands,
 	mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-	board: ResMut<BoardResource>
+	board: Res<BoardResource>
 ) -> Result<()> {
-	for (index, piece) in board.0.iter().enumerate() {
-		if let Some(piece) = piece {
-			let row = i8::try_from(index)? / BOARD_SIZE;
-			let col = i8::try_from(index)? % BOARD_SIZE;
-
-			let texture_atlas = TextureAtlas::from_grid(
-				PIECE_HANDLE.typed(),
-				Vec2::new(333.3, 333.3),
-				2,
-				1,
-				None,
-				Some(Vec2::new(
-					(piece.piece_type as i32) as f32 * 333.3,
-					(piece.color as i32) as f32 * 333.3
-				))
-			);
-			let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-			commands
-				.spawn(SpriteSheetBundle {
-					texture_atlas: texture_atlas_handle,
-					transform: Transform {
-						translation: Vec3::new(
-							Coord::to_win_piece(col),
-							Coord::to_win_piece(row),
-							2.0
-						),
-						scale: Vec3::splat(WINDOW_SIZE / 2500.),
-						..default()
-					},
-
-					..default()
-				})
-				.insert(Piece {
-					piece_type: piece.piece_type,
-					color: piece.color,
-					amount_moved: piece.amount_moved,
-					pos: Position::new(row, col)
-				});
-		}
-	}
+	spawn_piece_sprites(&mut commands, &mut texture_atlases, &board, &[0; 64]);
 
 	spawn_sprite_bundle!(
 		commands,
 		Color::rgba_u8(255, 255, 0, 100),
 		Vec3::new(Coord::to_win(0.5, 0.), Coord::to_win(0.5, 0.), 1.0),
-		HighlightSquare
+		(HighlightSquare, GameEntity)
 	);
 
 	spawn_sprite_bundle!(
 		commands,
 		Color::rgba_u8(200, 115, 0, 100),
 		Vec3::new(Coord::to_win(-0.5, 0.), Coord::to_win(-0.5, 0.), 1.0),
-		MovedSquare
+		(MovedSquare, GameEntity)
+	);
+
+	spawn_sprite_bundle!(
+		commands,
+		Color::rgba_u8(255, 0, 0, 120),
+		Vec3::new(Coord::to_win(-0.5, 0.), Coord::to_win(-0.5, 0.), 1.0),
+		(CheckSquare, GameEntity)
 	);
 
 	// Hover ------------------------------------------------
@@ -222,7 +243,7 @@ fn spawn_piece_sprites_system(
 			Fill::color(Color::NONE),
 			Stroke::new(Color::WHITE, 0.09)
 		))
-		.insert(HoverSquare);
+		.insert((HoverSquare, GameEntity));
 	Ok(())
 }
 
@@ -239,7 +260,7 @@ fn spawn_timers_system(mut commands: Commands) {
 		Color::WHITE,
 		Vec3::new(50. - (WINDOW_SIZE / 2.), -30. - (WINDOW_SIZE / 2.), 2.,),
 		text_style.clone(),
-		WhiteTimer
+		(WhiteTimer, GameEntity)
 	);
 	spawn_text_bundle!(
 		commands,
@@ -247,7 +268,7 @@ fn spawn_timers_system(mut commands: Commands) {
 		Color::WHITE,
 		Vec3::new(50. - (WINDOW_SIZE / 2.), 30. + (WINDOW_SIZE / 2.), 2.,),
 		text_style,
-		BlackTimer
+		(BlackTimer, GameEntity)
 	);
 }
 
@@ -265,6 +286,10 @@ fn update_white_timer_system(
 	mut white_timer: Query<&mut Text, With<WhiteTimer>>
 ) -> Result<()> {
 	let mut text = white_timer.get_single_mut()?;
+	if timers.time_control.is_unlimited() {
+		text.sections[0].value = String::from("--:--");
+		return Ok(())
+	}
 	let seconds = timers.white.duration().as_secs() - timers.white.elapsed().as_secs();
 
 	text.sections[0].value = format_elapsed_time(seconds);
@@ -277,26 +302,16 @@ fn update_black_timer_system(
 	mut black_timer: Query<&mut Text, With<BlackTimer>>
 ) -> Result<()> {
 	let mut text = black_timer.get_single_mut()?;
+	if timers.time_control.is_unlimited() {
+		text.sections[0].value = String::from("--:--");
+		return Ok(())
+	}
 	let seconds = timers.black.duration().as_secs() - timers.black.elapsed().as_secs();
 
 	text.sections[0].value = format_elapsed_time(seconds);
 	Ok(())
 }
 
-fn countdown(
-	time: Res<Time>,
-	mut countdown: ResMut<GameTimers>,
-	mut ev_exit: EventWriter<AppExit>
-) {
-	if countdown.white.finished() {
-		println!("Black WINS!!!");
-		ev_exit.send(AppExit);
-	}
-	if countdown.black.finished() {
-		println!("White WINS!!!");
-		ev_exit.send(AppExit);
-	}
-
-	countdown.white.tick(time.delta());
-	countdown.black.tick(time.delta());
+fn countdown(time: Res<Time>, mut countdown: ResMut<GameTimers>) {
+	countdown.tick(time.delta());
 }
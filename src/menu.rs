@@ -0,0 +1,463 @@
+#![allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
+
+use anyhow::{Context, Result};
+use bevy::{app::AppExit, prelude::*, sprite::Anchor, window::ReceivedCharacter};
+use strum::IntoEnumIterator;
+
+use crate::{
+	binary::FONT_HANDLE,
+	components::{
+		in_game, Analysis, AppState, Board, BoardResource, FinishedGame, GameEntity, GameHistory,
+		GameOutcome, GameResult, GameSetup, GameTimers, MenuEntity, NewGameEvent, PendingPromotion,
+		PieceColor, PlayerKind, PositionHistory, SaveGameEvent, SelectedPiece, SkillLevel
+	},
+	external::ExternalEngines,
+	pgn::game_to_pgn,
+	piece::end_turn,
+	WINDOW_SIZE
+};
+
+/// Time controls to pick from on the setup screen, with the names they're shown by
+const TIME_CONTROLS: [(&str, &str); 8] = [
+	("1 min", "60"),
+	("3 min + 2 s", "180+2"),
+	("5 min", "300"),
+	("10 min + 5 s", "600+5"),
+	("15 min + 10 s", "900+10"),
+	("90 min + 30 s", "5400+30"),
+	("40 moves in 90 min, then 30 min + 30 s", "40/5400:1800+30"),
+	("unlimited", "-")
+];
+
+const BUTTON_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<GameSetup>()
+			.add_systems(
+				OnEnter(AppState::Menu),
+				(
+					despawn_all::<GameEntity>,
+					despawn_all::<MenuEntity>,
+					reset_camera_system,
+					spawn_menu_system
+				)
+			)
+			.add_systems(
+				OnEnter(AppState::Setup),
+				(
+					despawn_all::<GameEntity>,
+					despawn_all::<MenuEntity>,
+					reset_camera_system,
+					spawn_setup_system
+				)
+			)
+			.add_systems(OnExit(AppState::Setup), despawn_all::<MenuEntity>)
+			.add_systems(OnEnter(AppState::GameOver), spawn_game_over_system)
+			.add_systems(OnExit(AppState::GameOver), despawn_all::<MenuEntity>)
+			.add_systems(
+				Update,
+				(
+					click_menu_button_system,
+					type_fen_system.run_if(in_state(AppState::Setup)),
+					update_menu_labels_system
+				)
+					.chain()
+					.run_if(not(in_state(AppState::Playing)))
+			)
+			.add_systems(Update, (leave_game_system, keep_pieces_upright_system).run_if(in_game()));
+	}
+}
+
+/// What a button of the menu or the setup screen does when clicked
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub enum MenuButton {
+	NewGame,
+	Quit,
+	Player(PieceColor),
+	TimeControl,
+	Position,
+	Orientation,
+	/// Whether taking a move back gives back the time spent on it
+	RefundClock,
+	Start,
+	Back,
+	/// Plays again with the colours swapped
+	Rematch,
+	ExportPgn,
+	/// Goes on from the final position without clocks, moving both sides by hand
+	Analyze,
+}
+
+/// Text showing the current choice of a setup button
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+pub struct MenuLabel(MenuButton);
+
+impl MenuButton {
+	fn label(self, setup: &GameSetup, engines: &ExternalEngines) -> String {
+		match self {
+			Self::NewGame => String::from("New game"),
+			Self::Quit => String::from("Quit"),
+			Self::Player(color) => match setup.player(color) {
+				PlayerKind::External(index) if index < engines.0.len() => {
+					format!("{color:?}: {}", engines.0[index].name)
+				}
+				player => format!("{color:?}: {player}")
+			},
+			Self::TimeControl => {
+				let pgn = setup.time_control.to_string();
+				let preset = TIME_CONTROLS.iter().find(|(_, x)| *x == pgn);
+				format!("Time: {}", preset.map_or(pgn.as_str(), |x| x.0))
+			}
+			Self::Position => {
+				if setup.fen == Board::STARTING_FEN {
+					String::from("Position: standard")
+				} else if setup.fen.is_empty() {
+					String::from("Position: type a FEN")
+				} else if Board::from_fen(&setup.fen).is_err() {
+					format!("{} (invalid)", setup.fen)
+				} else {
+					setup.fen.clone()
+				}
+			}
+			Self::Orientation => format!("{:?} at the bottom", setup.orientation),
+			Self::RefundClock => {
+				let refund = if setup.refund_clock { "yes" } else { "no" };
+				format!("Undo gives the time back: {refund}")
+			}
+			Self::Start => String::from("Start"),
+			Self::Back => String::from("Back"),
+			Self::Rematch => String::from("Rematch"),
+			Self::ExportPgn => String::from("Export PGN"),
+			Self::Analyze => String::from("Analyze")
+		}
+	}
+}
+
+pub fn despawn_all<T: Component>(mut commands: Commands, entities: Query<Entity, With<T>>) {
+	for entity in entities.iter() {
+		commands.entity(entity).despawn_recursive();
+	}
+}
+
+fn reset_camera_system(mut cameras: Query<&mut Transform, With<Camera>>) {
+	for mut transform in cameras.iter_mut() {
+		transform.rotation = Quat::IDENTITY;
+	}
+}
+
+/// Spawns a button and its label around `translation`, returning both entities
+fn spawn_button(
+	commands: &mut Commands,
+	button: MenuButton,
+	translation: Vec3,
+	width: f32
+) -> [Entity; 2] {
+	let sprite = commands
+		.spawn((
+			SpriteBundle {
+				sprite: Sprite {
+					color: BUTTON_COLOR,
+					custom_size: Some(Vec2::new(width, 50.)),
+					..default()
+				},
+				transform: Transform::from_translation(translation),
+				..default()
+			},
+			button,
+			MenuEntity
+		))
+		.id();
+
+	let label = spawn_text(commands, "", translation + Vec3::Z, 20.);
+	commands.entity(label).insert(MenuLabel(button));
+	[sprite, label]
+}
+
+fn spawn_text(commands: &mut Commands, text: &str, translation: Vec3, font_size: f32) -> Entity {
+	let text_style = TextStyle {
+		font: FONT_HANDLE.typed(),
+		font_size,
+		color: Color::WHITE
+	};
+	commands
+		.spawn((
+			Text2dBundle {
+				text: Text::from_section(text, text_style),
+				transform: Transform::from_translation(translation),
+				text_anchor: Anchor::Center,
+				..default()
+			},
+			MenuEntity
+		))
+		.id()
+}
+
+fn spawn_menu_system(mut commands: Commands) {
+	spawn_text(&mut commands, "Chess", Vec3::new(0., 150., 2.), 48.);
+	spawn_button(&mut commands, MenuButton::NewGame, Vec3::new(0., 0., 1.), 300.);
+	spawn_button(&mut commands, MenuButton::Quit, Vec3::new(0., -80., 1.), 300.);
+}
+
+fn spawn_setup_system(mut commands: Commands) {
+	spawn_text(&mut commands, "New game", Vec3::new(0., 280., 2.), 48.);
+
+	let options = [
+		MenuButton::Player(PieceColor::White),
+		MenuButton::Player(PieceColor::Black),
+		MenuButton::TimeControl,
+		MenuButton::Position,
+		MenuButton::Orientation,
+		MenuButton::RefundClock
+	];
+	for (y, button) in [205., 145., 85., 25., -35., -95.].into_iter().zip(options) {
+		spawn_button(&mut commands, button, Vec3::new(0., y, 1.), WINDOW_SIZE - 40.);
+	}
+
+	spawn_button(&mut commands, MenuButton::Start, Vec3::new(0., -185., 1.), 300.);
+	spawn_button(&mut commands, MenuButton::Back, Vec3::new(0., -255., 1.), 300.);
+}
+
+/// Shows how the game ended over the board and what to do next. It hangs off the camera, so it
+/// stays upright in the middle of the window whichever side is at the bottom
+fn spawn_game_over_system(
+	mut commands: Commands,
+	outcome: Res<GameOutcome>,
+	cameras: Query<Entity, With<Camera>>
+) {
+	let Ok(camera) = cameras.get_single() else { return };
+	let Some(event) = outcome.0 else { return };
+
+	// The camera looks at everything in front of it from 1000 units away, so its children are
+	// pushed far back to still be seen in front of the board
+	let depth = -900.;
+	let result = match event.result {
+		GameResult::WhiteWins => "White wins",
+		GameResult::BlackWins => "Black wins",
+		GameResult::Draw => "Draw"
+	};
+
+	let mut entities = vec![
+		commands
+			.spawn((
+				SpriteBundle {
+					sprite: Sprite {
+						color: Color::rgba(0., 0., 0., 0.8),
+						custom_size: Some(Vec2::new(400., 380.)),
+						..default()
+					},
+					transform: Transform::from_xyz(0., 0., depth),
+					..default()
+				},
+				MenuEntity
+			))
+			.id(),
+		spawn_text(&mut commands, result, Vec3::new(0., 140., depth + 1.), 36.),
+		spawn_text(
+			&mut commands,
+			&format!("by {}", event.reason),
+			Vec3::new(0., 100., depth + 1.),
+			20.
+		)
+	];
+
+	let buttons = [
+		MenuButton::Rematch,
+		MenuButton::NewGame,
+		MenuButton::ExportPgn,
+		MenuButton::Analyze
+	];
+	for (y, button) in [40., -20., -80., -140.].into_iter().zip(buttons) {
+		let translation = Vec3::new(0., y, depth + 1.);
+		entities.extend(spawn_button(&mut commands, button, translation, 300.));
+	}
+	commands.entity(camera).push_children(&entities);
+}
+
+/// Buttons react to a left click on them, setup options go on to their next choice
+fn click_menu_button_system(
+	mouse_button_input: Res<Input<MouseButton>>,
+	windows: Query<&Window>,
+	buttons: Query<(&MenuButton, &Transform, &Sprite)>,
+	mut setup: ResMut<GameSetup>,
+	engines: Res<ExternalEngines>,
+	mut analysis: ResMut<Analysis>,
+	mut finished: ResMut<FinishedGame>,
+	history: Res<GameHistory>,
+	timers: Res<GameTimers>,
+	mut outcome: ResMut<GameOutcome>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut ev_new_game: EventWriter<NewGameEvent>,
+	mut ev_save: EventWriter<SaveGameEvent>,
+	mut ev_exit: EventWriter<AppExit>
+) {
+	if !mouse_button_input.just_pressed(MouseButton::Left) {
+		return
+	}
+	let Some(cursor) = windows.get_single().ok().and_then(Window::cursor_position) else {
+		return
+	};
+	let world = Vec2::new(cursor.x - WINDOW_SIZE / 2., (WINDOW_SIZE + 100.) / 2. - cursor.y);
+
+	let clicked = buttons.iter().find(|(_, transform, sprite)| {
+		let half_size = sprite.custom_size.unwrap_or_default() / 2.;
+		let offset = (world - transform.translation.truncate()).abs();
+		offset.x <= half_size.x && offset.y <= half_size.y
+	});
+	let Some((&button, ..)) = clicked else { return };
+
+	match button {
+		MenuButton::NewGame => next_state.set(AppState::Setup),
+		MenuButton::Quit => ev_exit.send(AppExit),
+		MenuButton::Player(color) => {
+			let player = match color {
+				PieceColor::White => &mut setup.white,
+				PieceColor::Black => &mut setup.black
+			};
+			// From a human through the engine's levels, weakest first, then the external engines
+			// and back to a human
+			let next = std::iter::once(PlayerKind::Human)
+				.chain(SkillLevel::iter().map(PlayerKind::Engine))
+				.chain((0..engines.0.len()).map(PlayerKind::External))
+				.skip_while(|x| *x != *player)
+				.nth(1);
+			*player = next.unwrap_or(PlayerKind::Human);
+		}
+		MenuButton::TimeControl => {
+			let current = setup.time_control.to_string();
+			let index = TIME_CONTROLS.iter().position(|(_, x)| *x == current);
+			let next = index.map_or(0, |x| (x + 1) % TIME_CONTROLS.len());
+			if let Ok(time_control) = TIME_CONTROLS[next].1.parse() {
+				setup.time_control = time_control;
+			}
+		}
+		// Clicking switches between the standard position and typing in a FEN of its own
+		MenuButton::Position => {
+			if setup.fen == Board::STARTING_FEN {
+				setup.fen.clear();
+			} else {
+				setup.fen = Board::STARTING_FEN.to_string();
+			}
+		}
+		MenuButton::Orientation => setup.orientation = setup.orientation.not(),
+		MenuButton::RefundClock => setup.refund_clock = !setup.refund_clock,
+		MenuButton::Start => {
+			if Board::from_fen(&setup.fen).is_ok() {
+				ev_new_game.send(NewGameEvent);
+			}
+		}
+		MenuButton::Back => next_state.set(AppState::Menu),
+		MenuButton::Rematch => {
+			let setup = &mut *setup;
+			std::mem::swap(&mut setup.white, &mut setup.black);
+			setup.orientation = setup.orientation.not();
+			ev_new_game.send(NewGameEvent);
+		}
+		MenuButton::ExportPgn => ev_save.send(SaveGameEvent),
+		MenuButton::Analyze => {
+			finished.0 = Some(game_to_pgn(&history, &outcome, &timers));
+			analysis.0 = true;
+			outcome.0 = None;
+			next_state.set(AppState::Playing);
+		}
+	}
+}
+
+/// Typing on the setup screen edits the FEN of the starting position
+fn type_fen_system(
+	mut ev_character: EventReader<ReceivedCharacter>,
+	keys: Res<Input<KeyCode>>,
+	mut setup: ResMut<GameSetup>
+) {
+	for event in ev_character.iter() {
+		if !event.char.is_control() {
+			if setup.fen == Board::STARTING_FEN {
+				setup.fen.clear();
+			}
+			setup.fen.push(event.char);
+		}
+	}
+	if keys.just_pressed(KeyCode::Back) {
+		setup.fen.pop();
+	}
+}
+
+fn update_menu_labels_system(
+	setup: Res<GameSetup>,
+	engines: Res<ExternalEngines>,
+	mut labels: Query<(&MenuLabel, &mut Text)>
+) {
+	for (MenuLabel(button), mut text) in labels.iter_mut() {
+		let label = button.label(&setup, &engines);
+		if text.sections[0].value != label {
+			text.sections[0].value = label;
+		}
+	}
+}
+
+/// Sets up the resources of a new game from the setup screen, before its board is spawned
+pub fn start_game_system(
+	setup: Res<GameSetup>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut analysis: ResMut<Analysis>,
+	mut finished: ResMut<FinishedGame>,
+	mut board: ResMut<BoardResource>,
+	mut history: ResMut<GameHistory>,
+	mut position_history: ResMut<PositionHistory>,
+	mut timers: ResMut<GameTimers>,
+	mut selected_piece: ResMut<SelectedPiece>,
+	mut promotion: ResMut<PendingPromotion>,
+	mut outcome: ResMut<GameOutcome>,
+	mut cameras: Query<&mut Transform, With<Camera>>
+) -> Result<()> {
+	board.0 = Board::from_fen(&setup.fen).context("invalid starting position")?;
+
+	history.start = board.0.clone();
+	history.moves.clear();
+	history.undone.clear();
+	history.refund_clock = setup.refund_clock;
+	position_history.0 = vec![board.zobrist_key()];
+
+	*timers = GameTimers::new(setup.time_control.clone());
+	end_turn(board.side_to_move.not(), &mut timers);
+
+	selected_piece.0 = None;
+	promotion.0 = None;
+	outcome.0 = None;
+	analysis.0 = false;
+	finished.0 = None;
+	next_state.set(AppState::Playing);
+
+	// With black at the bottom the whole board is looked at from the other side
+	let rotation = match setup.orientation {
+		PieceColor::White => Quat::IDENTITY,
+		PieceColor::Black => Quat::from_rotation_z(std::f32::consts::PI)
+	};
+	for mut transform in cameras.iter_mut() {
+		transform.rotation = rotation;
+	}
+
+	Ok(())
+}
+
+/// Escape gives up on the game and goes back to the menu
+fn leave_game_system(keys: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<AppState>>) {
+	if keys.just_pressed(KeyCode::Escape) {
+		next_state.set(AppState::Menu);
+	}
+}
+
+/// Pieces and text turn with the camera, so they stand upright whichever side is at the bottom
+fn keep_pieces_upright_system(
+	cameras: Query<&Transform, With<Camera>>,
+	mut sprites: Query<&mut Transform, (With<GameEntity>, Without<Camera>)>
+) {
+	let Ok(camera) = cameras.get_single() else { return };
+	for mut transform in sprites.iter_mut() {
+		if transform.rotation != camera.rotation {
+			transform.rotation = camera.rotation;
+		}
+	}
+}
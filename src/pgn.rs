@@ -0,0 +1,147 @@
+#![allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
+
+use std::fs;
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use chess_rules::PgnMove;
+use chrono::Local;
+
+use crate::{
+	components::{
+		in_game, FinishedGame, GameHistory, GameOutcome, GameOverReason, GameTimers, LoadGameEvent,
+		PgnGame, PlayedMove, RestoreHistoryEvent, SaveGameEvent
+	},
+	util::error_handler
+};
+
+/// Non-standard tag with the full time control when it has delays, which `TimeControl` can't hold
+const TIME_CONTROL_DELAY_TAG: &str = "TimeControlDelay";
+
+pub struct PgnPlugin;
+
+impl Plugin for PgnPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				save_shortcut_system,
+				save_game_system.pipe(error_handler),
+				open_dropped_game_system.pipe(error_handler),
+				load_game_system
+			)
+				.chain()
+				.run_if(in_game())
+		);
+	}
+}
+
+/// Writes down the game played so far, with the clock times after every move
+pub fn game_to_pgn(history: &GameHistory, outcome: &GameOutcome, timers: &GameTimers) -> PgnGame {
+	let mut game = PgnGame::new(history.start.clone());
+	game.set_tag("Event", "Casual game");
+	game.set_tag("Date", &Local::now().format("%Y.%m.%d").to_string());
+	game.set_tag("TimeControl", &timers.time_control.to_pgn());
+	if timers.time_control.has_delay() {
+		game.set_tag(TIME_CONTROL_DELAY_TAG, &timers.time_control.to_string());
+	}
+	game.set_result(outcome.0.map(|x| x.result));
+	match outcome.0.map(|x| x.reason) {
+		Some(GameOverReason::Timeout | GameOverReason::TimeoutVsInsufficientMaterial) => {
+			game.set_tag("Termination", "time forfeit");
+		}
+		Some(GameOverReason::Abandoned) => game.set_tag("Termination", "abandoned"),
+		_ => {}
+	}
+
+	for played in &history.moves {
+		let mut pgn_move = PgnMove::new(played.mv);
+		if let Some(clock) = played.clock {
+			pgn_move.set_clock(clock);
+		}
+		game.moves.push(pgn_move);
+	}
+	game
+}
+
+/// Ctrl+S saves the game
+fn save_shortcut_system(keys: Res<Input<KeyCode>>, mut ev_save: EventWriter<SaveGameEvent>) {
+	let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+	if ctrl && keys.just_pressed(KeyCode::S) {
+		ev_save.send(SaveGameEvent);
+	}
+}
+
+/// Saves the game as a PGN file in the working directory. During analysis that's still the game as
+/// it ended
+fn save_game_system(
+	mut ev_save: EventReader<SaveGameEvent>,
+	finished: Res<FinishedGame>,
+	history: Res<GameHistory>,
+	outcome: Res<GameOutcome>,
+	timers: Res<GameTimers>
+) -> Result<()> {
+	if ev_save.iter().count() == 0 {
+		return Ok(())
+	}
+
+	let path = format!("{}.pgn", Local::now().format("game-%Y%m%d-%H%M%S"));
+	let game = finished.0.clone().unwrap_or_else(|| game_to_pgn(&history, &outcome, &timers));
+	fs::write(&path, game.to_string()).with_context(|| format!("failed to write {path}"))?;
+	info!("Saved game to {path}");
+
+	Ok(())
+}
+
+/// Dropping a PGN file on the window loads its first game
+fn open_dropped_game_system(
+	mut ev_drop: EventReader<FileDragAndDrop>,
+	mut ev_load: EventWriter<LoadGameEvent>
+) -> Result<()> {
+	for event in ev_drop.iter() {
+		let FileDragAndDrop::DroppedFile { path_buf, .. } = event else { continue };
+
+		let pgn = fs::read_to_string(path_buf)
+			.with_context(|| format!("failed to read {}", path_buf.display()))?;
+		let games = PgnGame::parse_all(&pgn)
+			.with_context(|| format!("failed to load {}", path_buf.display()))?;
+		if let Some(game) = games.into_iter().next() {
+			ev_load.send(LoadGameEvent(game));
+		}
+	}
+
+	Ok(())
+}
+
+/// Replaces the game history with the main line of a loaded game, ready to play on from its end
+fn load_game_system(
+	mut ev_load: EventReader<LoadGameEvent>,
+	mut history: ResMut<GameHistory>,
+	mut finished: ResMut<FinishedGame>,
+	mut timers: ResMut<GameTimers>,
+	mut ev_restore: EventWriter<RestoreHistoryEvent>
+) {
+	let Some(LoadGameEvent(game)) = ev_load.iter().last() else { return };
+
+	// Games without a time control we understand are played on with the current one
+	let tag = game.tag(TIME_CONTROL_DELAY_TAG).or_else(|| game.tag("TimeControl"));
+	if let Some(time_control) = tag.and_then(|x| x.parse().ok()) {
+		timers.time_control = time_control;
+	}
+
+	// The loaded game is the one played on and saved from now on
+	finished.0 = None;
+	history.start = game.start.clone();
+	history.undone.clear();
+	history.moves = game
+		.moves
+		.iter()
+		.map(|x| PlayedMove {
+			mv: x.mv,
+			clock: x.clock()
+		})
+		.collect();
+	ev_restore.send(RestoreHistoryEvent {
+		restore_clocks: true
+	});
+}
@@ -9,18 +9,17 @@
 	clippy::needless_pass_by_value
 )]
 
-use std::collections::HashMap;
-
 use anyhow::Result;
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 
 use crate::{
 	components::{
-		BoardResource, Coord, GameTimers, HighlightSquare, HoverEvent, HoverSquare, LegalMoveEvent,
-		LegalMoveMarker, MoveData, MoveEvent, MovedSquare, Piece, PieceColor, Pieces, Position,
-		SelectedPiece, TakeEvent
+		in_game, Analysis, AppState, Board, BoardResource, CheckEvent, CheckSquare, Coord,
+		GameEntity, GameHistory, GameSetup, GameTimers, HighlightSquare, HoverEvent, HoverSquare,
+		LegalMoveEvent, LegalMoveMarker, Move, MoveEvent, MovedSquare, PendingPromotion, Piece,
+		PieceColor, PlayMoveEvent, PlayedMove, PlayerKind, Position, SelectedPiece, TakeEvent
 	},
-	util::error_handler,
+	util::{error_handler, piece_texture_atlas},
 	BOARD_SIZE, SQUARE_SIZE, WINDOW_SIZE
 };
 
@@ -31,348 +30,184 @@ impl Plugin for PiecePlugin {
 		app.add_systems(
 			Update,
 			(
-				move_piece_system,
-				highlight_moved_system.pipe(error_handler),
-				highlight_selected_system.pipe(error_handler),
-				highlight_hover_system.pipe(error_handler),
-				highlight_legal_moves_system
+				move_piece_system
+					.run_if(in_state(AppState::Playing))
+					.run_if(resource_equals(PendingPromotion(None))),
+				apply_move_system.after(move_piece_system),
+				(
+					highlight_moved_system.pipe(error_handler),
+					highlight_selected_system.pipe(error_handler),
+					highlight_hover_system.pipe(error_handler),
+					highlight_check_system.pipe(error_handler),
+					highlight_legal_moves_system
+				)
+					.run_if(in_game())
 			)
 		);
 	}
 }
 
-struct LegalMoveGen<'a> {
-	piece: Piece,
-	start_square: i8,
-	squares_to_edge: &'a HashMap<usize, Vec<i8>>,
-	direction_offsets: [i8; 8],
-	board: &'a [Option<Piece>; 64],
-	turn_color: PieceColor,
-	moves: Vec<i8>
+/// Squares the piece on `square` can legally move to, promotions only counted once
+fn legal_targets(board: &Board, square: i8) -> Vec<i8> {
+	let mut targets: Vec<i8> = board.legal_moves_from(square).iter().map(|x| x.to).collect();
+	targets.dedup();
+	targets
 }
 
-impl LegalMoveGen<'_> {
-	fn sliding_moves(&mut self) -> Option<()> {
-		let start_dir_index = if self.piece.piece_type == Pieces::Bishop {
-			4
-		} else {
-			0
-		};
-
-		let end_dir_index = if self.piece.piece_type == Pieces::Rook {
-			4
-		} else {
-			8
-		};
-
-		for direction_index in start_dir_index..end_dir_index {
-			let direction_index = direction_index as usize;
-			for n in 0..self.squares_to_edge.get(&(self.start_square as usize))?[direction_index] {
-				let target_square =
-					(self.start_square + self.direction_offsets[direction_index] * (n + 1));
-				let piece_on_target_square = self.board[target_square as usize];
-
-				// Blocked by friendly piece, so can't move any further in this direction
-				if piece_on_target_square.is_some_and(|x| x.color == self.turn_color) {
-					break
-				}
-
-				self.moves.push(target_square);
-
-				// Can't move any further in this directoin after capturing opponent's piece
-				if piece_on_target_square.is_some_and(|x| x.color != self.turn_color) {
-					break
-				}
-			}
-		}
-		Some(())
-	}
-
-	fn pawn_moves(&mut self) -> Option<()> {
-		let valid_offsets = if self.piece.color == PieceColor::White {
-			([8, 16], [9, 7])
-		} else {
-			([-8, -16], [-9, -7])
-		};
-		for offset in valid_offsets.0 {
-			let target_square = (self.start_square + offset);
-			let piece_on_target_square = self.board[target_square as usize];
-
-			if offset.abs() == 8
-				&& piece_on_target_square.is_some_and(|x| x.color != self.turn_color)
-			{
-				break
-			}
-
-			// Blocked by friendly piece, so can't move any further in this direction
-			if piece_on_target_square.is_some_and(|x| x.color == self.turn_color) {
-				break
-			}
-			if offset.abs() == 16 && self.piece.amount_moved != 0 {
-				break
-			}
-
-			self.moves.push(target_square);
-
-			// Can't move any further in this directoin after capturing opponent's piece
-			if piece_on_target_square.is_some_and(|x| x.color != self.turn_color) {
-				break
-			}
-		}
-		for offset in valid_offsets.1 {
-			let target_square = (self.start_square + offset);
-			let piece_on_target_square = self.board[target_square as usize];
-
-			// Blocked by friendly piece, so can't move any further in this direction
-			if piece_on_target_square.is_some_and(|x| x.color == self.turn_color) {
-				break
-			}
-
-			if piece_on_target_square.is_none()
-				|| piece_on_target_square.is_some_and(|x| x.color != self.piece.color.not())
-			{
-				continue
-			}
-
-			self.moves.push(target_square);
-
-			// Can't move any further in this directoin after capturing opponent's piece
-			if piece_on_target_square.is_some_and(|x| x.color != self.turn_color) {
-				break
-			}
-		}
-		Some(())
-	}
-
-	fn knight_moves(&mut self) -> Option<()> {
-		let directions = [
-			(1, 2),
-			(2, 1),
-			(-1, 2),
-			(-2, 1),
-			(1, -2),
-			(2, -1),
-			(-1, -2),
-			(-2, -1)
-		];
-
-		let calculate_index = |row: i8, col: i8| (row * BOARD_SIZE + col);
-		for &(dir_x, dir_y) in &directions {
-			let new_row = self.piece.pos.row + dir_x;
-			let new_col = self.piece.pos.col + dir_y;
-
-			if (0..8).contains(&new_row) && (0..8).contains(&new_col) {
-				let index = calculate_index(new_row, new_col);
-				let piece_on_target_square = self.board[index as usize];
-				if piece_on_target_square.is_some_and(|x| x.color == self.piece.color) {
-					continue
-				}
-				self.moves.push(index);
-			}
+/// Stops the clock of the side that just moved and starts the one of its opponent
+pub fn end_turn(turn_color: PieceColor, timers: &mut GameTimers) {
+	match turn_color {
+		PieceColor::White => {
+			timers.black.unpause();
+			timers.white.pause();
 		}
-		Some(())
-	}
-
-	fn king_moves(&mut self) -> Option<()> {
-		for (index, offset) in self.direction_offsets.iter().enumerate() {
-			if self.squares_to_edge.get(&(self.start_square as usize))?[index] > 0 {
-				let target_square = (self.start_square + offset);
-				let piece_on_target_square = self.board[target_square as usize];
-
-				// Blocked by friendly piece, so can't move any further in this direction
-				if piece_on_target_square.is_some_and(|x| x.color == self.turn_color) {
-					continue
-				}
-				self.moves.push(target_square);
-			}
+		PieceColor::Black => {
+			timers.white.unpause();
+			timers.black.pause();
 		}
-		Some(())
 	}
 }
 
-fn get_legal_moves(
-	squares_to_edge: &HashMap<usize, Vec<i8>>,
-	direction_offsets: [i8; 8],
-	board: &[Option<Piece>; 64],
-	start_square: i8,
-	turn_color: PieceColor
-) -> Vec<i8> {
-	let mut moves = Vec::new();
-	let piece = board[start_square as usize].unwrap();
-	let mut legal_move_gen = LegalMoveGen {
-		piece,
-		start_square,
-		squares_to_edge,
-		direction_offsets,
-		board,
-		turn_color,
-		moves
-	};
-	match piece.piece_type {
-		Pieces::Queen | Pieces::Rook | Pieces::Bishop => legal_move_gen.sliding_moves(),
-		Pieces::Knight => legal_move_gen.knight_moves(),
-		Pieces::Pawn => legal_move_gen.pawn_moves(),
-		Pieces::King => legal_move_gen.king_moves()
-	};
-
-	legal_move_gen.moves
+/// Spawns a sprite for every piece on `board`, with how often the piece on each square has moved
+pub fn spawn_piece_sprites(
+	commands: &mut Commands,
+	texture_atlases: &mut Assets<TextureAtlas>,
+	board: &Board,
+	amount_moved: &[u32; 64]
+) {
+	for (index, piece) in board.pieces() {
+		let Position { row, col } = Position::from_index(index);
+
+		let texture_atlas = piece_texture_atlas(piece.piece_type, piece.color);
+		let texture_atlas_handle = texture_atlases.add(texture_atlas);
+
+		commands
+			.spawn(SpriteSheetBundle {
+				texture_atlas: texture_atlas_handle,
+				transform: Transform {
+					translation: Vec3::new(
+						Coord::to_win_piece(col),
+						Coord::to_win_piece(row),
+						2.0
+					),
+					scale: Vec3::splat(WINDOW_SIZE / 2500.),
+					..default()
+				},
+
+				..default()
+			})
+			.insert((
+				Piece {
+					piece_type: piece.piece_type,
+					color: piece.color,
+					amount_moved: amount_moved[index as usize],
+					pos: Position::new(row, col)
+				},
+				GameEntity
+			));
+	}
 }
 
 fn move_piece_system(
 	mouse_button_input: Res<Input<MouseButton>>,
 	windows: Query<&Window>,
-	mut board: ResMut<BoardResource>,
+	board: Res<BoardResource>,
 	mut selected_piece: ResMut<SelectedPiece>,
-	mut pieces: Query<(&mut Piece, &mut Transform, Entity)>,
-	mut next_state: ResMut<NextState<PieceColor>>,
-	current_state: Res<State<PieceColor>>,
-	mut commands: Commands,
+	mut pieces: Query<(&Piece, &mut Transform)>,
 	mut ev_move: EventWriter<MoveEvent>,
 	mut ev_hover: EventWriter<HoverEvent>,
-	mut ev_take: EventWriter<TakeEvent>,
 	mut ev_legal: EventWriter<LegalMoveEvent>,
-	move_info: Res<MoveData>,
-	mut timers: ResMut<GameTimers>
+	mut ev_play: EventWriter<PlayMoveEvent>,
+	mut promotion: ResMut<PendingPromotion>,
+	setup: Res<GameSetup>,
+	analysis: Res<Analysis>
 ) {
+	// Engines make their own moves, only when analysing both sides are moved by hand
+	if !analysis.0 && setup.player(board.side_to_move) != PlayerKind::Human {
+		return
+	}
+
 	let window = windows.get_single().unwrap();
-	#[allow(clippy::cast_possible_truncation)]
-	if let Some(position) = window.cursor_position() {
-		let x = position[0];
-		let y = position[1] - 50.;
+	let cursor = window.cursor_position().map(|x| Coord::orient_cursor(x, setup.orientation));
+	// The square under the cursor, and the cursor itself for dragging the piece along
+	let hovered = cursor.and_then(|x| Some((x, Coord::from_cursor(x)?)));
+	if let Some((position, clicked_position)) = hovered {
+		let Position { row, col } = clicked_position;
+		let turn_color = board.side_to_move;
+		let clicked_index = clicked_position.index();
+
+		let clicked_piece = board.piece_at(clicked_index);
+		if mouse_button_input.just_pressed(MouseButton::Left) {
+			if selected_piece.0 == Some(clicked_position) {
+				// if piece is already selected deselect it
+				selected_piece.0 = None;
+				ev_legal.send(LegalMoveEvent::default());
+				ev_move.send(MoveEvent::default());
+			} else if clicked_piece.is_some_and(|x| x.color == turn_color) {
+				// if piece isnt selected select it
+				selected_piece.0 = Some(clicked_position);
+
+				let legal_moves = legal_targets(&board, clicked_index);
+				ev_legal.send(LegalMoveEvent(Some(legal_moves)));
+				ev_move.send(MoveEvent::default());
+				ev_hover.send(HoverEvent::default());
+			};
+		}
+		if mouse_button_input.pressed(MouseButton::Left) {
+			if let Some(selected) = selected_piece.0 {
+				let legal_moves = legal_targets(&board, selected.index());
 
-		let col = ((x / 75.).floor()) as i8;
-		let row = 7 - ((y / 75.).floor()) as i8;
-		if (0. ..600.).contains(&y) && (0. ..600.).contains(&x) {
-			let turn_color = *current_state.get();
-			let index = (row * BOARD_SIZE + col) as usize;
+				ev_legal.send(LegalMoveEvent(Some(legal_moves.clone())));
 
-			let clicked_piece = board.0[index];
-			if mouse_button_input.just_pressed(MouseButton::Left) {
-				if clicked_piece == selected_piece.0 && selected_piece.0.is_some() {
-					// if piece is already selected deselect it
-					selected_piece.0 = None;
-					ev_legal.send(LegalMoveEvent::default());
-					ev_move.send(MoveEvent::default());
+				if legal_moves.contains(&clicked_index) {
+					ev_hover.send(HoverEvent(Some(clicked_position)));
 				} else if clicked_piece.is_some_and(|x| x.color == turn_color) {
-					// if piece isnt selected select it
-					selected_piece.0 = clicked_piece;
-					let selected = selected_piece.0.unwrap();
-					let selected_index = selected.pos.row * BOARD_SIZE + selected.pos.col;
-
-					let legal_moves = get_legal_moves(
-						&move_info.clone().num_squares_to_edge,
-						move_info.direction_offsets,
-						&board.0,
-						selected_index,
-						turn_color
-					);
-					ev_legal.send(LegalMoveEvent(Some(legal_moves)));
-					ev_move.send(MoveEvent::default());
 					ev_hover.send(HoverEvent::default());
-				};
-			}
-			if mouse_button_input.pressed(MouseButton::Left) {
-				if let Some(selected) = selected_piece.0 {
-					let selected_index = selected.pos.row * BOARD_SIZE + selected.pos.col;
-
-					let legal_moves = get_legal_moves(
-						&move_info.clone().num_squares_to_edge,
-						move_info.direction_offsets,
-						&board.0,
-						selected_index,
-						turn_color
-					);
-
-					ev_legal.send(LegalMoveEvent(Some(legal_moves.clone())));
-					let clicked_index = row * BOARD_SIZE + col;
-
-					if (clicked_piece.is_none()
-						|| clicked_piece.is_some_and(|x| x.color != turn_color))
-						&& legal_moves.contains(&(clicked_index))
-					{
-						ev_hover.send(HoverEvent(Some(Position::new(row, col))));
-					} else if clicked_piece.is_some_and(|x| x.color == turn_color) {
-						ev_hover.send(HoverEvent::default());
-					}
-					for (piece, mut transform, _) in pieces.iter_mut() {
-						if Some(*piece) == selected_piece.0 {
-							transform.translation.x = position.x - (WINDOW_SIZE / 2.);
-							transform.translation.y = -position.y + (WINDOW_SIZE / 2.) + 50.;
-							transform.translation.z = 30.;
-						}
+				}
+				for (piece, mut transform) in pieces.iter_mut() {
+					if piece.pos == selected {
+						transform.translation.x = position.x - (WINDOW_SIZE / 2.);
+						transform.translation.y = -position.y + (WINDOW_SIZE / 2.) + 50.;
+						transform.translation.z = 30.;
 					}
 				}
 			}
-			if mouse_button_input.just_released(MouseButton::Left) {
-				ev_hover.send(HoverEvent::default());
-
-				if let Some(selected) = selected_piece.0 {
-					let selected_index = selected.pos.row * BOARD_SIZE + selected.pos.col;
+		}
+		if mouse_button_input.just_released(MouseButton::Left) {
+			ev_hover.send(HoverEvent::default());
 
-					let legal_moves = get_legal_moves(
-						&move_info.clone().num_squares_to_edge,
-						move_info.direction_offsets,
-						&board.0,
-						selected_index,
-						turn_color
-					);
+			if let Some(selected) = selected_piece.0 {
+				let chosen_move = board
+					.legal_moves_from(selected.index())
+					.into_iter()
+					.find(|x| x.to == clicked_index);
 
-					let clicked_index = row * BOARD_SIZE + col;
-					if Some(selected) != clicked_piece
-						&& (clicked_piece.is_none()
-							|| clicked_piece.is_some_and(|x| x.color != turn_color))
-						&& legal_moves.contains(&{ clicked_index })
-					{
-						for (mut piece, mut transform, entity) in pieces.iter_mut() {
-							if piece.as_ref() == &selected {
+				if let Some(chosen_move) = chosen_move {
+					selected_piece.0 = None;
+					ev_legal.send(LegalMoveEvent::default());
+					if chosen_move.promotion.is_some() {
+						// The move is only played once the player picked the new piece, until
+						// then the pawn waits on the promotion square
+						for (piece, mut transform) in pieces.iter_mut() {
+							if piece.pos == selected {
 								transform.translation.x = Coord::to_win_piece(col);
 								transform.translation.y = Coord::to_win_piece(row);
-
 								transform.translation.z = 2.;
-								piece.amount_moved += 1;
-								let new_position = Position::new(row, col);
-								piece.pos = new_position;
-
-								let old_index =
-									(selected.pos.row * BOARD_SIZE + selected.pos.col) as usize;
-
-								board.0[old_index] = None;
-
-								board.0[index] = Some(*piece);
-								board.0[index].map(|mut x| new_position);
-
-								ev_move.send(MoveEvent(Some(new_position)));
-							}
-							if clicked_piece.is_some_and(|x| x == *piece.as_ref())
-								&& clicked_piece.is_some_and(|x| x.color != turn_color)
-							{
-								commands.entity(entity).despawn_recursive();
-								ev_take.send(TakeEvent);
 							}
 						}
-						selected_piece.0 = None;
-						ev_legal.send(LegalMoveEvent::default());
-						next_state.set(turn_color.not());
-						match turn_color {
-							PieceColor::White => {
-								timers.black.unpause();
-								timers.white.pause();
-							}
-							PieceColor::Black => {
-								timers.white.unpause();
-								timers.black.pause();
-							}
-						}
-					} else if Some(selected) == clicked_piece
-						|| clicked_piece.is_some_and(|x| x.color == turn_color)
-						|| !legal_moves.contains(&{ clicked_index })
-					{
-						for (piece, mut transform, _) in pieces.iter_mut() {
-							if piece.as_ref() == &selected {
-								transform.translation.x = Coord::to_win_piece(piece.pos.col);
-								transform.translation.y = Coord::to_win_piece(piece.pos.row);
-								transform.translation.z = 2.;
-							}
+						promotion.0 = Some(Move {
+							promotion: None,
+							..chosen_move
+						});
+					} else {
+						ev_play.send(PlayMoveEvent(chosen_move));
+					}
+				} else {
+					for (piece, mut transform) in pieces.iter_mut() {
+						if piece.pos == selected {
+							transform.translation.x = Coord::to_win_piece(piece.pos.col);
+							transform.translation.y = Coord::to_win_piece(piece.pos.row);
+							transform.translation.z = 2.;
 						}
 					}
 				}
@@ -381,14 +216,71 @@ fn move_piece_system(
 	}
 }
 
+/// Plays moves on the board and brings the sprites in line with it, whoever made the move
+fn apply_move_system(
+	mut ev_play: EventReader<PlayMoveEvent>,
+	mut board: ResMut<BoardResource>,
+	mut pieces: Query<(&mut Piece, &mut Transform, &mut Handle<TextureAtlas>, Entity)>,
+	mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+	mut commands: Commands,
+	mut ev_move: EventWriter<MoveEvent>,
+	mut ev_take: EventWriter<TakeEvent>,
+	mut timers: ResMut<GameTimers>,
+	mut history: ResMut<GameHistory>
+) {
+	for PlayMoveEvent(mv) in ev_play.iter() {
+		let captured_square = mv.en_passant_capture_square().unwrap_or(mv.to);
+		let castling_rook = mv.castling_rook_squares();
+
+		for (mut piece, mut transform, mut texture_atlas, entity) in pieces.iter_mut() {
+			if piece.pos == Position::from_index(mv.from) {
+				piece.pos = Position::from_index(mv.to);
+				if let Some(piece_type) = mv.promotion {
+					piece.piece_type = piece_type;
+					*texture_atlas =
+						texture_atlases.add(piece_texture_atlas(piece_type, piece.color));
+				}
+			} else if mv.is_capture() && piece.pos == Position::from_index(captured_square) {
+				commands.entity(entity).despawn_recursive();
+				ev_take.send(TakeEvent);
+				continue
+			} else if let Some((_, rook_target)) = castling_rook
+				.filter(|(rook_start, _)| piece.pos == Position::from_index(*rook_start))
+			{
+				// When castling the rook jumps over the king together with it
+				piece.pos = Position::from_index(rook_target);
+			} else {
+				continue
+			}
+
+			piece.amount_moved += 1;
+			transform.translation.x = Coord::to_win_piece(piece.pos.col);
+			transform.translation.y = Coord::to_win_piece(piece.pos.row);
+			transform.translation.z = 2.;
+		}
+
+		board.make_move(*mv);
+		ev_move.send(MoveEvent(Some(Position::from_index(mv.to))));
+		timers.complete_move(mv.piece.color);
+		end_turn(mv.piece.color, &mut timers);
+
+		// Without clocks the timers sit at `Duration::MAX`, which isn't worth recording
+		let clock = (!timers.time_control.is_unlimited())
+			.then(|| timers.timer(mv.piece.color).remaining());
+		history.moves.push(PlayedMove { mv: *mv, clock });
+		// Playing a move of its own drops the moves that were taken back
+		history.undone.clear();
+	}
+}
+
 fn highlight_selected_system(
 	selected: Res<SelectedPiece>,
 	mut highlight_square: Query<(&HighlightSquare, &mut Transform)>
 ) -> Result<()> {
 	let mut highlight_square = highlight_square.get_single_mut()?;
 	if let Some(selected) = selected.0 {
-		highlight_square.1.translation.x = Coord::to_win_piece(selected.pos.col);
-		highlight_square.1.translation.y = Coord::to_win_piece(selected.pos.row);
+		highlight_square.1.translation.x = Coord::to_win_piece(selected.col);
+		highlight_square.1.translation.y = Coord::to_win_piece(selected.row);
 	} else {
 		highlight_square.1.translation.x = Coord::to_win_piece(-1.);
 		highlight_square.1.translation.y = Coord::to_win_piece(-1.);
@@ -432,6 +324,24 @@ fn highlight_hover_system(
 	Ok(())
 }
 
+fn highlight_check_system(
+	mut check_square: Query<(&CheckSquare, &mut Transform)>,
+	mut ev_check: EventReader<CheckEvent>
+) -> Result<()> {
+	let mut check_square = check_square.get_single_mut()?;
+	for event in ev_check.iter() {
+		if let Some(position) = event.0 {
+			check_square.1.translation.x = Coord::to_win_piece(position.col);
+			check_square.1.translation.y = Coord::to_win_piece(position.row);
+		} else {
+			check_square.1.translation.x = Coord::to_win_piece(-1.);
+			check_square.1.translation.y = Coord::to_win_piece(-1.);
+		}
+	}
+
+	Ok(())
+}
+
 fn highlight_legal_moves_system(
 	mut ev_legal: EventReader<LegalMoveEvent>,
 	mut commands: Commands,
@@ -462,7 +372,7 @@ fn highlight_legal_moves_system(
 						},
 						..default()
 					})
-					.insert(LegalMoveMarker);
+					.insert((LegalMoveMarker, GameEntity));
 			}
 		} else {
 			for (_, entity) in markers.iter_mut() {
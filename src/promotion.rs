@@ -0,0 +1,105 @@
+#![allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
+
+use bevy::prelude::*;
+
+use crate::{
+	components::{
+		BoardResource, Coord, GameEntity, GameSetup, PendingPromotion, PieceColor, Pieces,
+		PlayMoveEvent, Position, PromotionChoice, PromotionPicker
+	},
+	util::{macros::spawn_sprite_bundle, piece_texture_atlas},
+	SQUARE_SIZE, WINDOW_SIZE
+};
+
+const PROMOTION_CHOICES: [Pieces; 4] = [Pieces::Queen, Pieces::Rook, Pieces::Bishop, Pieces::Knight];
+
+pub struct PromotionPlugin;
+
+impl Plugin for PromotionPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				update_promotion_picker_system.run_if(resource_changed::<PendingPromotion>()),
+				choose_promotion_system
+			)
+		);
+	}
+}
+
+/// Shows the four pieces a pawn can turn into on the promotion file, counting down from the last
+/// rank, and removes them again once the choice is made
+fn update_promotion_picker_system(
+	mut commands: Commands,
+	mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+	promotion: Res<PendingPromotion>,
+	board: Res<BoardResource>,
+	pickers: Query<Entity, With<PromotionPicker>>
+) {
+	for entity in pickers.iter() {
+		commands.entity(entity).despawn_recursive();
+	}
+
+	let Some(promotion_move) = promotion.0 else { return };
+	let Some(pawn) = board.piece_at(promotion_move.from) else { return };
+	let position = Position::from_index(promotion_move.to);
+	let direction = if pawn.color == PieceColor::White { -1 } else { 1 };
+
+	for (offset, piece_type) in (0..).zip(PROMOTION_CHOICES) {
+		let pos = Position::new(position.row + direction * offset, position.col);
+
+		spawn_sprite_bundle!(
+			commands,
+			Color::rgb_u8(245, 245, 245),
+			Vec3::new(Coord::to_win_piece(pos.col), Coord::to_win_piece(pos.row), 40.0),
+			(PromotionPicker, GameEntity)
+		);
+
+		commands.spawn((
+			SpriteSheetBundle {
+				texture_atlas: texture_atlases.add(piece_texture_atlas(piece_type, pawn.color)),
+				transform: Transform {
+					translation: Vec3::new(
+						Coord::to_win_piece(pos.col),
+						Coord::to_win_piece(pos.row),
+						41.0
+					),
+					scale: Vec3::splat(WINDOW_SIZE / 2500.),
+					..default()
+				},
+				..default()
+			},
+			PromotionPicker,
+			PromotionChoice { piece_type, pos },
+			GameEntity
+		));
+	}
+}
+
+fn choose_promotion_system(
+	mouse_button_input: Res<Input<MouseButton>>,
+	windows: Query<&Window>,
+	choices: Query<&PromotionChoice>,
+	mut promotion: ResMut<PendingPromotion>,
+	mut ev_play: EventWriter<PlayMoveEvent>,
+	setup: Res<GameSetup>
+) {
+	let Some(promotion_move) = promotion.0 else { return };
+	if !mouse_button_input.just_pressed(MouseButton::Left) {
+		return
+	}
+
+	let Some(clicked) = windows
+		.get_single()
+		.ok()
+		.and_then(Window::cursor_position)
+		.map(|x| Coord::orient_cursor(x, setup.orientation))
+		.and_then(Coord::from_cursor)
+	else {
+		return
+	};
+	let Some(choice) = choices.iter().find(|x| x.pos == clicked) else { return };
+
+	promotion.0 = None;
+	ev_play.send(PlayMoveEvent(promotion_move.with_promotion(choice.piece_type)));
+}
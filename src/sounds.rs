@@ -2,7 +2,7 @@ use bevy::{audio::Volume, prelude::*};
 
 use crate::{
 	binary::{MOVE_SOUND_HANDLE, START_SOUND_HANDLE, TAKE_SOUND_HANDLE},
-	components::{MoveEvent, TakeEvent}
+	components::{MoveEvent, NewGameEvent, TakeEvent}
 };
 
 pub struct SoundPlugin;
@@ -10,7 +10,7 @@ pub struct SoundPlugin;
 impl Plugin for SoundPlugin {
 	fn build(&self, app: &mut App) {
 		app.add_systems(Update, (play_take_sound_system, play_move_sound_system))
-			.add_systems(Startup, play_start_sound);
+			.add_systems(Update, play_start_sound.run_if(on_event::<NewGameEvent>()));
 	}
 }
 
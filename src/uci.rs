@@ -0,0 +1,351 @@
+use std::{
+	io::{self, BufRead},
+	path::Path,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc
+	},
+	thread::{self, JoinHandle},
+	time::Duration
+};
+
+use anyhow::{bail, Context, Result};
+use chess_rules::{
+	Board, CastlingMode, Move, PieceColor, Score, SearchInfo, SearchLimits, Searcher, SkillLevel
+};
+use strum::IntoEnumIterator;
+
+use crate::engine::{load_eval_params, EVAL_PARAMS_FILE};
+
+const ENGINE_NAME: &str = "chess_bevy";
+const DEFAULT_HASH: usize = 16;
+const MAX_HASH: usize = 4096;
+/// Highest `Skill Level`, the usual range of UCI engines
+const MAX_SKILL: usize = 20;
+const MAX_MULTI_PV: usize = 64;
+
+/// `chess uci` plays the built-in engine over the UCI protocol on stdin and stdout, for other GUIs
+/// and tournament managers
+pub fn run() -> Result<()> {
+	let mut engine = UciEngine::new()?;
+	for line in io::stdin().lock().lines() {
+		let line = line.context("failed to read from stdin")?;
+		if !engine.handle(&line)? {
+			break
+		}
+	}
+
+	engine.stop_search();
+	Ok(())
+}
+
+/// A search running on its own thread, which hands the searcher back when it's done
+struct RunningSearch {
+	thread: JoinHandle<Searcher>,
+	stop: Arc<AtomicBool>,
+	/// Holds the best move back until `stop` or `ponderhit`, as `go infinite` and `go ponder` ask
+	hold: Arc<AtomicBool>,
+	/// Thinking time from the moment the pondered move is played
+	ponder_time: Option<Duration>
+}
+
+struct UciEngine {
+	board: Board,
+	/// Keys of the positions before `board`, for repetitions
+	keys: Vec<u64>,
+	/// `None` while a search has it
+	searcher: Option<Searcher>,
+	search: Option<RunningSearch>,
+	/// The searcher's level, which also shortens the time left after `ponderhit`
+	skill: SkillLevel
+}
+
+impl UciEngine {
+	/// Evaluates with the weights in [`EVAL_PARAMS_FILE`] if there is one, like the game does
+	fn new() -> Result<Self> {
+		let mut searcher = Searcher::new(DEFAULT_HASH);
+		let path = Path::new(EVAL_PARAMS_FILE);
+		if path.exists() {
+			searcher.set_eval_params(load_eval_params(path)?);
+		}
+
+		Ok(Self {
+			board: Board::starting_position(),
+			keys: Vec::new(),
+			searcher: Some(searcher),
+			search: None,
+			skill: SkillLevel::default()
+		})
+	}
+
+	/// Answers one line from the GUI, returning `false` once the engine should quit. Malformed
+	/// commands are ignored as the protocol asks, but a bad position is reported on stderr
+	fn handle(&mut self, line: &str) -> Result<bool> {
+		let tokens: Vec<&str> = line.split_whitespace().collect();
+		let Some((&command, args)) = tokens.split_first() else { return Ok(true) };
+
+		match command {
+			"uci" => {
+				println!("id name {ENGINE_NAME}");
+				println!("id author the {ENGINE_NAME} authors");
+				println!("option name Hash type spin default {DEFAULT_HASH} min 1 max {MAX_HASH}");
+				// The search runs on one thread, GUIs send the option anyway
+				println!("option name Threads type spin default 1 min 1 max 1");
+				println!("option name Ponder type check default false");
+				println!(
+					"option name Skill Level type spin default {MAX_SKILL} min 0 max {MAX_SKILL}"
+				);
+				println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+				println!("uciok");
+			}
+			"isready" => println!("readyok"),
+			"ucinewgame" => self.searcher()?.clear(),
+			"setoption" => self.set_option(args)?,
+			"position" => {
+				if let Err(err) = self.set_position(args) {
+					eprintln!("{err:?}");
+				}
+			}
+			"go" => self.go(args)?,
+			"stop" => self.stop_search(),
+			"ponderhit" => self.ponder_hit(),
+			"quit" => return Ok(false),
+			_ => {}
+		}
+
+		Ok(true)
+	}
+
+	/// The searcher, after waiting for a running search to hand it back
+	fn searcher(&mut self) -> Result<&mut Searcher> {
+		self.stop_search();
+		self.searcher.as_mut().context("the search thread panicked")
+	}
+
+	/// Stops a running search, which then sends its best move
+	fn stop_search(&mut self) {
+		let Some(search) = self.search.take() else { return };
+		search.stop.store(true, Ordering::Relaxed);
+		search.hold.store(false, Ordering::Relaxed);
+		self.searcher = search.thread.join().ok();
+	}
+
+	/// The opponent played the move that was pondered on, so the search goes on as a normal one
+	fn ponder_hit(&mut self) {
+		let Some(search) = &mut self.search else { return };
+		search.hold.store(false, Ordering::Relaxed);
+
+		let Some(time) = search.ponder_time.take() else { return };
+		let stop = search.stop.clone();
+		thread::spawn(move || {
+			thread::sleep(time);
+			stop.store(true, Ordering::Relaxed);
+		});
+	}
+
+	/// `setoption name <name> value <value>`, where the name can have spaces
+	fn set_option(&mut self, args: &[&str]) -> Result<()> {
+		let value_at = args.iter().position(|&x| x == "value").unwrap_or(args.len());
+		let name = args[..value_at].iter().skip(1).copied().collect::<Vec<_>>().join(" ");
+		let value = args.get(value_at + 1..).unwrap_or_default().join(" ");
+		let number = || value.parse::<usize>().ok();
+
+		let searcher = self.searcher()?;
+		match name.to_lowercase().as_str() {
+			"hash" => {
+				if let Some(megabytes) = number() {
+					searcher.resize_table(megabytes.clamp(1, MAX_HASH));
+				}
+			}
+			"skill level" => {
+				if let Some(level) = number() {
+					searcher.set_skill_level(skill_level(level));
+					self.skill = skill_level(level);
+				}
+			}
+			"multipv" => {
+				if let Some(lines) = number() {
+					searcher.set_multi_pv(lines.clamp(1, MAX_MULTI_PV));
+				}
+			}
+			_ => {}
+		}
+
+		Ok(())
+	}
+
+	/// `position [startpos | fen <fen>] [moves <move>...]`
+	fn set_position(&mut self, args: &[&str]) -> Result<()> {
+		let moves_at = args.iter().position(|&x| x == "moves").unwrap_or(args.len());
+		let (setup, moves) = args.split_at(moves_at);
+
+		let mut board = match setup {
+			["startpos"] => Board::starting_position(),
+			["fen", fen @ ..] => {
+				let fen = fen.join(" ");
+				Board::from_fen(&fen).with_context(|| format!("invalid FEN '{fen}'"))?
+			}
+			_ => bail!("expected startpos or fen in 'position {}'", args.join(" "))
+		};
+
+		let mut keys = Vec::new();
+		for uci in moves.iter().skip(1) {
+			let mv = board.parse_uci(uci).with_context(|| format!("invalid move '{uci}'"))?;
+			keys.push(board.zobrist_key());
+			board.make_move(mv);
+		}
+
+		self.board = board;
+		self.keys = keys;
+		Ok(())
+	}
+
+	/// `go` with any of its limits, searching on another thread so `stop` can still be read
+	fn go(&mut self, args: &[&str]) -> Result<()> {
+		self.searcher()?;
+		let Some(mut searcher) = self.searcher.take() else { return Ok(()) };
+
+		let go = GoParams::parse(args);
+		let color = self.board.side_to_move;
+		let mut limits = SearchLimits {
+			depth: go.depth,
+			nodes: go.nodes,
+			time: go.move_time
+		};
+		if let Some(remaining) = go.time[color as usize] {
+			let increment = go.increment[color as usize];
+			let clock = SearchLimits::for_clock(remaining, increment, go.moves_to_go);
+			limits.time = limits.time.or(clock.time);
+		}
+
+		// A pondering search has no time limit until the opponent plays the pondered move
+		let ponder_time = if go.ponder {
+			self.skill.limit(SearchLimits { time: limits.time.take(), ..limits }).time
+		} else {
+			None
+		};
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let hold = Arc::new(AtomicBool::new(go.infinite || go.ponder));
+		let board = self.board.clone();
+		let keys = self.keys.clone();
+
+		let thread = {
+			let stop = stop.clone();
+			let hold = hold.clone();
+			thread::spawn(move || {
+				let mut best_line = Vec::new();
+				let best = searcher.search(&board, &keys, limits, &stop, |info| {
+					print_info(info);
+					if info.multi_pv == 1 {
+						best_line.clone_from(&info.pv);
+					}
+				});
+
+				while hold.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+					thread::sleep(Duration::from_millis(5));
+				}
+				print_best_move(best, &best_line);
+				searcher
+			})
+		};
+
+		self.search = Some(RunningSearch {
+			thread,
+			stop,
+			hold,
+			ponder_time
+		});
+		Ok(())
+	}
+}
+
+/// The arguments of `go`
+#[derive(Debug, Default)]
+struct GoParams {
+	/// Time left on the clocks, indexed by [`PieceColor`]
+	time: [Option<Duration>; 2],
+	increment: [Duration; 2],
+	moves_to_go: Option<u32>,
+	depth: Option<u8>,
+	nodes: Option<u64>,
+	move_time: Option<Duration>,
+	infinite: bool,
+	ponder: bool
+}
+
+impl GoParams {
+	fn parse(args: &[&str]) -> Self {
+		let mut go = Self::default();
+		let mut args = args.iter();
+		let white = PieceColor::White as usize;
+		let black = PieceColor::Black as usize;
+
+		while let Some(&name) = args.next() {
+			let mut number = || args.next().and_then(|x| x.parse::<i64>().ok());
+			// Some GUIs send negative times once a clock ran out
+			let mut millis = || number().map(|x| Duration::from_millis(x.max(0) as u64));
+			match name {
+				"wtime" => go.time[white] = millis(),
+				"btime" => go.time[black] = millis(),
+				"winc" => go.increment[white] = millis().unwrap_or_default(),
+				"binc" => go.increment[black] = millis().unwrap_or_default(),
+				"movestogo" => go.moves_to_go = number().and_then(|x| x.try_into().ok()),
+				"depth" => go.depth = number().and_then(|x| x.try_into().ok()),
+				"nodes" => go.nodes = number().and_then(|x| x.try_into().ok()),
+				"movetime" => go.move_time = millis(),
+				"infinite" => go.infinite = true,
+				"ponder" => go.ponder = true,
+				_ => {}
+			}
+		}
+
+		go
+	}
+}
+
+/// Spreads the 21 UCI skill levels over the engine's own ones
+fn skill_level(level: usize) -> SkillLevel {
+	let levels: Vec<_> = SkillLevel::iter().collect();
+	let index = level.min(MAX_SKILL) * (levels.len() - 1) / MAX_SKILL;
+	levels[index]
+}
+
+fn print_info(info: &SearchInfo) {
+	let score = match info.score {
+		Score::Centipawns(centipawns) => format!("cp {centipawns}"),
+		Score::Mate(moves) => format!("mate {moves}")
+	};
+	let millis = info.time.as_millis();
+	let nps = u128::from(info.nodes) * 1000 / millis.max(1);
+	let pv: Vec<_> = info.pv.iter().map(|&x| uci(x)).collect();
+
+	println!(
+		"info depth {} multipv {} score {score} nodes {} nps {nps} hashfull {} time {millis} pv {}",
+		info.depth,
+		info.multi_pv,
+		info.nodes,
+		info.hashfull,
+		pv.join(" ")
+	);
+}
+
+/// `bestmove`, with the reply expected to it to ponder on when there is one
+fn print_best_move(best: Option<Move>, best_line: &[Move]) {
+	let Some(best) = best else {
+		// Checkmate or stalemate, there's nothing to play
+		println!("bestmove 0000");
+		return
+	};
+
+	match best_line {
+		[first, reply, ..] if *first == best => {
+			println!("bestmove {} ponder {}", uci(best), uci(*reply));
+		}
+		_ => println!("bestmove {}", uci(best))
+	}
+}
+
+fn uci(mv: Move) -> String {
+	mv.to_uci(CastlingMode::Standard).to_string()
+}
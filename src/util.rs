@@ -1,9 +1,14 @@
 use anyhow::Result;
-use bevy::prelude::In;
+use bevy::prelude::{In, TextureAtlas, Vec2};
+
+use crate::{
+	binary::PIECE_HANDLE,
+	components::{PieceColor, Pieces}
+};
 
 pub const WINDOW_SIZE: f32 = 600.;
 pub const SQUARE_SIZE: f32 = WINDOW_SIZE / 8.;
-pub const BOARD_SIZE: i8 = 8;
+pub use chess_rules::BOARD_SIZE;
 
 pub mod macros {
 	macro_rules! spawn_sprite_bundle {
@@ -80,6 +85,20 @@ pub mod macros {
 	pub(crate) use spawn_text_bundle;
 }
 
+pub fn piece_texture_atlas(piece_type: Pieces, color: PieceColor) -> TextureAtlas {
+	TextureAtlas::from_grid(
+		PIECE_HANDLE.typed(),
+		Vec2::new(333.3, 333.3),
+		2,
+		1,
+		None,
+		Some(Vec2::new(
+			(piece_type as i32) as f32 * 333.3,
+			(color as i32) as f32 * 333.3
+		))
+	)
+}
+
 pub fn error_handler(In(result): In<Result<()>>) {
 	if let Err(err) = result {
 		eprintln!("encountered an error {err:?}");